@@ -0,0 +1,232 @@
+// Chat over WebSocket: ws://localhost:8080/ws/{name}
+//
+// Every connection is a `Session` actor, the sessions talk through
+// one `Hub` for the whole server (a system service, so the workers
+// share it). What a client sends is a line of text, what it receives
+// is JSON, its own messages included:
+//
+//   {"event":"join","name":"bruno"}
+//   {"event":"message","name":"bruno","text":"hello"}
+//   {"event":"leave","name":"bruno"}
+//
+// The server pings every HEARTBEAT_INTERVAL, a client that says
+// nothing for CLIENT_TIMEOUT is disconnected.
+
+use actix::prelude::*;
+use actix_web::{ws, HttpRequest, HttpResponse};
+use errors::Error;
+use serde_json;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use user_path::parse_name;
+use users::AppState;
+
+
+// in seconds
+pub const HEARTBEAT_INTERVAL: u64 = 5;
+pub const CLIENT_TIMEOUT: u64 = 10;
+// in characters, longer messages are cut
+pub const MAX_MESSAGE_LEN: usize = 1000;
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event<'a> {
+    Join { name: &'a str },
+    Message { name: &'a str, text: &'a str },
+    Leave { name: &'a str },
+}
+
+
+// --- Messages between the sessions and the hub ---
+
+// A JSON event for the client
+pub struct Line(pub String);
+
+impl Message for Line {
+    type Result = ();
+}
+
+// Answered with the id of the new session
+pub struct Join {
+    pub name: String,
+    pub session: Recipient<Syn, Line>,
+}
+
+impl Message for Join {
+    type Result = usize;
+}
+
+pub struct Leave {
+    pub id: usize,
+}
+
+impl Message for Leave {
+    type Result = ();
+}
+
+pub struct Say {
+    pub id: usize,
+    pub text: String,
+}
+
+impl Message for Say {
+    type Result = ();
+}
+
+
+// --- Hub ---
+
+struct Member {
+    name: String,
+    session: Recipient<Syn, Line>,
+}
+
+#[derive(Default)]
+pub struct Hub {
+    members: HashMap<usize, Member>,
+    next_id: usize,
+}
+
+impl Hub {
+    fn broadcast(&self, event: &Event) {
+        let line = serde_json::to_string(event).unwrap();
+        for member in self.members.values() {
+            // a session that is gone sends Leave soon
+            let _ = member.session.do_send(Line(line.clone()));
+        }
+    }
+}
+
+impl Actor for Hub {
+    type Context = Context<Self>;
+}
+
+impl Supervised for Hub {}
+
+impl SystemService for Hub {}
+
+impl Handler<Join> for Hub {
+    type Result = usize;
+
+    fn handle(&mut self, join: Join, _: &mut Context<Self>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.members.insert(id, Member { name: join.name, session: join.session });
+        self.broadcast(&Event::Join { name: &self.members[&id].name });
+        id
+    }
+}
+
+impl Handler<Leave> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, leave: Leave, _: &mut Context<Self>) {
+        if let Some(member) = self.members.remove(&leave.id) {
+            self.broadcast(&Event::Leave { name: &member.name });
+        }
+    }
+}
+
+impl Handler<Say> for Hub {
+    type Result = ();
+
+    fn handle(&mut self, say: Say, _: &mut Context<Self>) {
+        if let Some(member) = self.members.get(&say.id) {
+            self.broadcast(&Event::Message { name: &member.name, text: &say.text });
+        }
+    }
+}
+
+
+// --- Session ---
+
+pub struct Session {
+    // given by the hub once joined
+    id: Option<usize>,
+    name: String,
+    // last time the client was heard of
+    heartbeat: Instant,
+}
+
+impl Session {
+    fn new(name: String) -> Session {
+        Session { id: None, name, heartbeat: Instant::now() }
+    }
+
+    fn check_heartbeat(&self, ctx: &mut ws::WebsocketContext<Self, AppState>) {
+        ctx.run_interval(Duration::from_secs(HEARTBEAT_INTERVAL), |session, ctx| {
+            if session.heartbeat.elapsed() > Duration::from_secs(CLIENT_TIMEOUT) {
+                println!("Chat: {} timed out", session.name);
+                ctx.stop();
+            } else {
+                ctx.ping("");
+            }
+        });
+    }
+}
+
+impl Actor for Session {
+    type Context = ws::WebsocketContext<Self, AppState>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.check_heartbeat(ctx);
+        let me: Addr<Syn, _> = ctx.address();
+        let join = Join { name: self.name.clone(), session: me.recipient() };
+        // nothing is read from the client before the hub knows it
+        Arbiter::system_registry()
+            .get::<Hub>()
+            .send(join)
+            .into_actor(self)
+            .then(|joined, session, ctx| {
+                match joined {
+                    Ok(id) => session.id = Some(id),
+                    Err(_) => ctx.stop(),
+                }
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        if let Some(id) = self.id {
+            Arbiter::system_registry().get::<Hub>().do_send(Leave { id });
+        }
+        Running::Stop
+    }
+}
+
+impl Handler<Line> for Session {
+    type Result = ();
+
+    fn handle(&mut self, line: Line, ctx: &mut Self::Context) {
+        ctx.text(line.0);
+    }
+}
+
+impl StreamHandler<ws::Message, ws::ProtocolError> for Session {
+    fn handle(&mut self, message: ws::Message, ctx: &mut Self::Context) {
+        self.heartbeat = Instant::now();
+        match message {
+            ws::Message::Ping(message) => ctx.pong(&message),
+            ws::Message::Pong(_) => (),
+            ws::Message::Text(text) => {
+                let text: String = text.trim().chars().take(MAX_MESSAGE_LEN).collect();
+                if let (Some(id), false) = (self.id, text.is_empty()) {
+                    Arbiter::system_registry().get::<Hub>().do_send(Say { id, text });
+                }
+            }
+            // it is a text chat
+            ws::Message::Binary(_) => (),
+            ws::Message::Close(_) => ctx.stop(),
+        }
+    }
+}
+
+// GET /ws/{name}, the name follows the rules of the index route
+pub fn connect(req: HttpRequest<AppState>) -> Result<HttpResponse, ::actix_web::Error> {
+    let name = match parse_name(req.match_info().get("name").unwrap_or("")) {
+        Err(message) => return Err(Error::bad_request(&req, message).into()),
+        Ok(name) => name,
+    };
+    ws::start(req, Session::new(name))
+}
@@ -0,0 +1,177 @@
+// Server configuration, a TOML file named by the APP_CONFIG
+// environment variable. Without it every default is used.
+//
+// [server]
+// address = "0.0.0.0:8080"
+// workers = 4             # default: one per CPU
+// shutdown_timeout = 30   # seconds to finish the requests in flight
+//
+// [storage]
+// backend = "sqlite"   # or "memory"
+// path = "users.db"
+//
+// [static_files]        # see files.rs
+// dir = "../wasm-greet"
+// spa_fallback = true
+//
+// [middleware]          # see middleware.rs
+// trace = true
+// cors_origins = ["http://localhost:3000"]
+// compression = ["br", "gzip"]
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use toml;
+
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub static_files: StaticConfig,
+    pub middleware: MiddlewareConfig,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: String,
+    pub workers: Option<usize>,
+    pub shutdown_timeout: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            address: String::from("localhost:8080"),
+            workers: None,
+            shutdown_timeout: 30,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase", deny_unknown_fields)]
+pub enum StorageConfig {
+    Memory,
+    Sqlite { path: PathBuf },
+}
+
+impl Default for StorageConfig {
+    fn default() -> StorageConfig {
+        StorageConfig::Memory
+    }
+}
+
+// No dir, no static files
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StaticConfig {
+    pub dir: Option<PathBuf>,
+    pub spa_fallback: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MiddlewareConfig {
+    pub trace: bool,
+    pub cors_origins: Vec<String>,
+    pub compression: Vec<Encoding>,
+}
+
+impl Default for MiddlewareConfig {
+    fn default() -> MiddlewareConfig {
+        MiddlewareConfig {
+            trace: true,
+            cors_origins: Vec::new(),
+            compression: vec![Encoding::Br, Encoding::Gzip],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Br,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    // as in the Accept-Encoding header
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Br => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(source: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(source).map_err(|e| format!("Invalid config file: {}", e))?;
+        if config.server.workers == Some(0) {
+            return Err(String::from("Invalid config file: workers must be at least 1"));
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Config, String> {
+        let mut source = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("Can not read config file {}: {}", path, e))?;
+        Config::from_toml(&source)
+    }
+
+    pub fn load() -> Result<Config, String> {
+        match env::var("APP_CONFIG") {
+            Ok(path) => Config::from_file(&path),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+}
+
+
+#[test]
+fn test_storage_config() {
+  assert_eq!(Config::from_toml("").unwrap().storage, StorageConfig::Memory);
+  assert_eq!(Config::from_toml("[storage]\nbackend = \"memory\"").unwrap().storage, StorageConfig::Memory);
+  assert_eq!(
+      Config::from_toml("[storage]\nbackend = \"sqlite\"\npath = \"users.db\"").unwrap().storage,
+      StorageConfig::Sqlite { path: PathBuf::from("users.db") }
+  );
+  assert!(Config::from_toml("[storage]\nbackend = \"sqlite\"").is_err());
+  assert!(Config::from_toml("[storage]\nbackend = \"postgres\"").is_err());
+}
+
+#[test]
+fn test_static_config() {
+  assert_eq!(Config::from_toml("").unwrap().static_files.dir, None);
+  let config = Config::from_toml("[static_files]\ndir = \"../wasm-greet\"\nspa_fallback = true").unwrap();
+  assert_eq!(config.static_files, StaticConfig { dir: Some(PathBuf::from("../wasm-greet")), spa_fallback: true });
+}
+
+#[test]
+fn test_middleware_config() {
+  assert_eq!(Config::from_toml("").unwrap().middleware, MiddlewareConfig::default());
+  let config = Config::from_toml("[middleware]\ntrace = false\ncors_origins = [\"*\"]\ncompression = [\"gzip\"]").unwrap();
+  assert_eq!(config.middleware, MiddlewareConfig {
+      trace: false,
+      cors_origins: vec![String::from("*")],
+      compression: vec![Encoding::Gzip],
+  });
+  assert!(Config::from_toml("[middleware]\ncompression = [\"zstd\"]").is_err());
+}
+
+#[test]
+fn test_server_config() {
+  assert_eq!(Config::from_toml("").unwrap().server, ServerConfig::default());
+  let config = Config::from_toml("[server]\naddress = \"0.0.0.0:80\"\nworkers = 2").unwrap();
+  assert_eq!((config.server.address.as_str(), config.server.workers, config.server.shutdown_timeout), ("0.0.0.0:80", Some(2), 30));
+  assert!(Config::from_toml("[server]\nworkers = 0").is_err());
+}
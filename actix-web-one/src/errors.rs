@@ -0,0 +1,156 @@
+// Errors answered the same way by every route
+//
+// Clients asking for JSON (`Accept: application/json`) get
+//   {"status": 400, "error": "Bad Request", "message": "..."}
+// everybody else gets a small HTML page with the same information.
+
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use greeting::preferences;
+use std::error;
+use std::fmt;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Html,
+}
+
+impl Format {
+    // The pages are html, JSON only when the client prefers it,
+    // by the same q-values as the greeting (greeting.rs)
+    pub fn of<S>(req: &HttpRequest<S>) -> Format {
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        for media_type in preferences(accept) {
+            match media_type.as_str() {
+                "application/json" | "application/*" => return Format::Json,
+                "text/html" | "text/*" | "*/*" => return Format::Html,
+                _ => (),
+            }
+        }
+        Format::Html
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    status: u16,
+    error: &'a str,
+    message: &'a str,
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub status: StatusCode,
+    pub message: String,
+    pub format: Format,
+}
+
+impl Error {
+    pub fn new<S>(req: &HttpRequest<S>, status: StatusCode, message: String) -> Error {
+        Error { status, message, format: Format::of(req) }
+    }
+
+    // for the JSON API, whatever the client accepts
+    pub fn json(status: StatusCode, message: String) -> Error {
+        Error { status, message, format: Format::Json }
+    }
+
+    pub fn bad_request<S>(req: &HttpRequest<S>, message: String) -> Error {
+        Error::new(req, StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn not_found<S>(req: &HttpRequest<S>, message: String) -> Error {
+        Error::new(req, StatusCode::NOT_FOUND, message)
+    }
+
+    fn reason(&self) -> &'static str {
+        self.status.canonical_reason().unwrap_or("Error")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.message)
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+// actix-web calls this to turn an Err from an extractor or a handler
+// into the response
+impl ResponseError for Error {
+    fn error_response(&self) -> HttpResponse {
+        match self.format {
+            Format::Json => HttpResponse::build(self.status).json(ErrorBody {
+                status: self.status.as_u16(),
+                error: self.reason(),
+                message: &self.message,
+            }),
+            Format::Html => HttpResponse::build(self.status)
+                .content_type("text/html; charset=utf-8")
+                .body(format!(
+                    "<!DOCTYPE html>\n<html>\n<head><title>{status} {reason}</title></head>\n\
+                     <body>\n<h1>{status} {reason}</h1>\n<p>{message}</p>\n</body>\n</html>\n",
+                    status = self.status.as_u16(),
+                    reason = self.reason(),
+                    message = escape(&self.message)
+                )),
+        }
+    }
+}
+
+// Every path no route matched
+pub fn not_found<S>(req: HttpRequest<S>) -> HttpResponse {
+    let message = format!("Nothing here at {}", req.path());
+    Error::not_found(&req, message).error_response()
+}
+
+
+#[test]
+fn test_format() {
+  use actix_web::test::TestRequest;
+
+  assert_eq!(Format::of(&TestRequest::default().finish()), Format::Html);
+  let req = TestRequest::with_header("Accept", "application/json").finish();
+  assert_eq!(Format::of(&req), Format::Json);
+  // what browsers send
+  let req = TestRequest::with_header("Accept", "text/html,application/xhtml+xml,application/json;q=0.9").finish();
+  assert_eq!(Format::of(&req), Format::Html);
+  // the q-values decide, not the order or the mere presence of a type
+  let req = TestRequest::with_header("Accept", "application/json, text/html;q=0.1").finish();
+  assert_eq!(Format::of(&req), Format::Json);
+  let req = TestRequest::with_header("Accept", "text/html;q=0.5, application/json").finish();
+  assert_eq!(Format::of(&req), Format::Json);
+  let req = TestRequest::with_header("Accept", "application/json;q=0, */*").finish();
+  assert_eq!(Format::of(&req), Format::Html);
+}
+
+#[test]
+fn test_html_error_is_escaped() {
+  assert_eq!(escape("<b>\"Tom\" & 'Jerry'</b>"), "&lt;b&gt;&quot;Tom&quot; &amp; &#x27;Jerry&#x27;&lt;/b&gt;");
+}
@@ -0,0 +1,320 @@
+// Static files from the directory set in the config, served by the
+// default resource so every route of `app()` is matched first.
+//
+// - the Content-Type comes from the extension, see `MIME_TYPES`
+// - ETag and Last-Modified, answered with 304 Not Modified when unchanged
+// - a single `Range: bytes=...` is answered with 206 Partial Content
+// - single page apps: a path without an extension that is not a file
+//   nor a route, like /settings/profile, gets the root index.html and
+//   the app routes itself
+//
+// The ETag comes from the metadata, so a 304 never opens the file and
+// a range only reads its own bytes. The body is read in chunks while it
+// is sent, a large file is never held in memory.
+//
+// [static_files]
+// dir = "../wasm-greet"
+// spa_fallback = true
+
+use actix_web::http::header::{self, HttpDate};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use bytes::Bytes;
+use config::StaticConfig;
+use errors;
+use futures::{Async, Poll, Stream};
+use percent_encoding::percent_decode;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+// The most of a file read at once
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+const MIME_TYPES: [(&str, &str); 14] = [
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "application/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    // browsers only compile wasm streamed with this exact type
+    ("wasm", "application/wasm"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("txt", "text/plain; charset=utf-8"),
+];
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|&&(known, _)| known == extension)
+        .map(|&(_, mime)| mime)
+        .unwrap_or("application/octet-stream")
+}
+
+pub struct Files {
+    root: PathBuf,
+    spa_fallback: bool,
+}
+
+impl Files {
+    pub fn new(config: &StaticConfig) -> Option<Files> {
+        config.dir.as_ref().map(|dir| Files {
+            root: dir.clone(),
+            spa_fallback: config.spa_fallback,
+        })
+    }
+
+    // The file for an url path, None when the path tries to leave the
+    // root or points to a hidden file
+    fn resolve(&self, url_path: &str) -> Option<PathBuf> {
+        let decoded = percent_decode(url_path.as_bytes()).decode_utf8().ok()?;
+        let mut path = self.root.clone();
+        for segment in decoded.split('/').filter(|segment| !segment.is_empty()) {
+            if segment.starts_with('.') || segment.contains('\\') || segment.contains('\0') {
+                return None;
+            }
+            path.push(segment);
+        }
+        if path.is_dir() {
+            path.push("index.html");
+        }
+        Some(path)
+    }
+
+    // /settings/profile is an app route, /missing.js is a missing file
+    fn is_app_route(url_path: &str) -> bool {
+        let last = url_path.rsplit('/').next().unwrap_or("");
+        !last.contains('.')
+    }
+
+    pub fn serve<S>(&self, req: &HttpRequest<S>) -> HttpResponse {
+        if *req.method() != Method::GET && *req.method() != Method::HEAD {
+            return errors::not_found(req.clone());
+        }
+        let found = self.resolve(req.path()).filter(|path| path.is_file());
+        let path = match found {
+            Some(path) => path,
+            None if self.spa_fallback && Files::is_app_route(req.path()) => self.root.join("index.html"),
+            None => return errors::not_found(req.clone()),
+        };
+        match fs::metadata(&path) {
+            Ok(metadata) => respond(req, &path, &metadata),
+            Err(_) => errors::not_found(req.clone()),
+        }
+    }
+}
+
+// Last-Modified only has seconds, compare with that precision
+fn whole_seconds(time: SystemTime) -> SystemTime {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+fn etag(len: u64, modified: SystemTime) -> String {
+    let nanos = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("\"{:x}-{:x}.{:x}\"", len, nanos.as_secs(), nanos.subsec_nanos())
+}
+
+fn not_modified<S>(req: &HttpRequest<S>, etag: &str, modified: SystemTime) -> bool {
+    let headers = req.headers();
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        // when both are sent If-None-Match wins
+        return if_none_match.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag == etag || tag.trim_left_matches("W/") == etag
+        });
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<HttpDate>().ok())
+        .map(|since| whole_seconds(modified) <= SystemTime::from(since))
+        .unwrap_or(false)
+}
+
+// `bytes=0-99`, `bytes=100-` or `bytes=-100` as (first, last) included.
+// None to send the whole file: no range, a malformed one or several
+// ranges. Some(Err) when the range is outside the file.
+fn parse_range(range: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = range.trim();
+    if !spec.starts_with("bytes=") || spec.contains(',') {
+        return None;
+    }
+    let mut bounds = spec["bytes=".len()..].splitn(2, '-').map(|bound| bound.trim());
+    let (first, last) = (bounds.next()?, bounds.next()?);
+
+    let range = match (first.parse::<u64>(), last.parse::<u64>()) {
+        // the last `n` bytes
+        (Err(_), Ok(n)) if first.is_empty() => {
+            if n == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(n), len - 1)
+        }
+        (Ok(first), Err(_)) if last.is_empty() => (first, len.saturating_sub(1)),
+        (Ok(first), Ok(last)) if first <= last => (first, last.min(len.saturating_sub(1))),
+        _ => return None,
+    };
+    if range.0 >= len {
+        return Some(Err(()));
+    }
+    Some(Ok(range))
+}
+
+// The body of a file response, read a chunk at a time
+struct FileChunks {
+    file: io::Take<File>,
+}
+
+impl Stream for FileChunks {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, Error> {
+        let mut chunk = vec![0; CHUNK_SIZE.min(self.file.limit()) as usize];
+        let read = if chunk.is_empty() { 0 } else { self.file.read(&mut chunk)? };
+        if read == 0 {
+            return Ok(Async::Ready(None));
+        }
+        chunk.truncate(read);
+        Ok(Async::Ready(Some(Bytes::from(chunk))))
+    }
+}
+
+// `len` bytes from `first`, fewer when the file got shorter
+fn read_range(path: &Path, first: u64, len: u64) -> io::Result<FileChunks> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(first))?;
+    Ok(FileChunks { file: file.take(len) })
+}
+
+fn respond<S>(req: &HttpRequest<S>, path: &Path, metadata: &fs::Metadata) -> HttpResponse {
+    let len = metadata.len();
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let etag = etag(len, modified);
+    let last_modified = HttpDate::from(whole_seconds(modified)).to_string();
+
+    if not_modified(req, &etag, modified) {
+        return HttpResponse::NotModified()
+            .header(header::ETAG, etag)
+            .header(header::LAST_MODIFIED, last_modified)
+            .finish();
+    }
+
+    // a Range with an If-Range for an older version gets the whole file
+    let if_range_matches = match req.headers().get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        None => true,
+        Some(if_range) => if_range == etag || if_range == last_modified,
+    };
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| if_range_matches)
+        .and_then(|range| parse_range(range, len));
+
+    let mut response = match range {
+        None => HttpResponse::Ok(),
+        Some(Err(())) => {
+            return HttpResponse::build(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .finish()
+        }
+        Some(Ok((first, last))) => {
+            let mut partial = HttpResponse::build(StatusCode::PARTIAL_CONTENT);
+            partial.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", first, last, len));
+            partial
+        }
+    };
+    let (first, count) = match range {
+        Some(Ok((first, last))) => (first, last - first + 1),
+        _ => (0, len),
+    };
+    let chunks = match read_range(path, first, count) {
+        Ok(chunks) => chunks,
+        Err(_) => return errors::not_found(req.clone()),
+    };
+    response
+        .content_type(mime_type(path))
+        .header(header::ETAG, etag)
+        .header(header::LAST_MODIFIED, last_modified)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .streaming(chunks)
+}
+
+
+#[test]
+fn test_mime_type() {
+  assert_eq!(mime_type(Path::new("wasm_greet_bg.wasm")), "application/wasm");
+  assert_eq!(mime_type(Path::new("index.HTML")), "text/html; charset=utf-8");
+  assert_eq!(mime_type(Path::new("index.js")), "application/javascript; charset=utf-8");
+  assert_eq!(mime_type(Path::new("Makefile")), "application/octet-stream");
+}
+
+#[test]
+fn test_parse_range() {
+  assert_eq!(parse_range("bytes=0-9", 100), Some(Ok((0, 9))));
+  assert_eq!(parse_range("bytes=90-", 100), Some(Ok((90, 99))));
+  assert_eq!(parse_range("bytes=-10", 100), Some(Ok((90, 99))));
+  assert_eq!(parse_range("bytes=-500", 100), Some(Ok((0, 99))));
+  assert_eq!(parse_range("bytes=50-500", 100), Some(Ok((50, 99))));
+  assert_eq!(parse_range("bytes=100-", 100), Some(Err(())));
+  assert_eq!(parse_range("bytes=-0", 100), Some(Err(())));
+  assert_eq!(parse_range("bytes=9-0", 100), None);
+  assert_eq!(parse_range("bytes=0-1,5-6", 100), None);
+  assert_eq!(parse_range("lines=0-1", 100), None);
+}
+
+#[test]
+fn test_resolve() {
+  let files = Files { root: PathBuf::from("/srv/www"), spa_fallback: true };
+  assert_eq!(files.resolve("/js/app.js"), Some(PathBuf::from("/srv/www/js/app.js")));
+  assert_eq!(files.resolve("/my%20file.txt"), Some(PathBuf::from("/srv/www/my file.txt")));
+  assert_eq!(files.resolve("/../etc/passwd"), None);
+  assert_eq!(files.resolve("/%2e%2e/etc/passwd"), None);
+  assert_eq!(files.resolve("/.git/config"), None);
+  assert!(Files::is_app_route("/settings/profile"));
+  assert!(!Files::is_app_route("/missing.js"));
+}
+
+#[test]
+fn test_read_range() {
+  use futures::Future;
+
+  let read = |path: &Path, first: u64, len: u64| read_range(path, first, len).unwrap().concat2().wait().unwrap().to_vec();
+  let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+  let whole = fs::read(path).unwrap();
+  assert_eq!(read(path, 0, whole.len() as u64), whole);
+  assert_eq!(read(path, 3, 5), &whole[3..8]);
+  // past the end there is nothing more to read
+  assert_eq!(read(path, whole.len() as u64 - 2, 10), &whole[whole.len() - 2..]);
+}
+
+#[test]
+fn test_large_files_are_read_in_chunks() {
+  use futures::Future;
+  use std::env;
+
+  let path = env::temp_dir().join(format!("actix-web-one-chunks-{}", ::std::process::id()));
+  fs::write(&path, vec![7u8; 3 * CHUNK_SIZE as usize + 10]).unwrap();
+  let chunks = read_range(&path, 0, 3 * CHUNK_SIZE + 10).unwrap().collect().wait().unwrap();
+  let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.len()).collect();
+  assert_eq!(sizes, vec![CHUNK_SIZE as usize, CHUNK_SIZE as usize, CHUNK_SIZE as usize, 10]);
+  // a range stops at its own end
+  let chunks = read_range(&path, 5, CHUNK_SIZE + 1).unwrap().collect().wait().unwrap();
+  assert_eq!(chunks.iter().map(|chunk| chunk.len()).sum::<usize>(), CHUNK_SIZE as usize + 1);
+  fs::remove_file(&path).unwrap();
+}
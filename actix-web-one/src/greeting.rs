@@ -0,0 +1,180 @@
+// The greeting of `index` in the format and the language the client asks for
+//
+// Format, from `?format=` or else the `Accept` header:
+//   text/plain (the default), application/json, text/html
+//   anything else is 406 Not Acceptable
+//
+// Language, from the `Accept-Language` header, English when none of the
+// languages in `GREETINGS` is accepted.
+
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpRequest, HttpResponse};
+use errors::{escape, Error};
+use user_path::UserPath;
+
+
+// (language, greeting)
+const GREETINGS: [(&str, &str); 6] = [
+    ("en", "Hello"),
+    ("pt", "Olá"),
+    ("es", "Hola"),
+    ("fr", "Bonjour"),
+    ("de", "Hallo"),
+    ("it", "Ciao"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Html,
+}
+
+// `text/html;q=0.8` -> ("text/html", 0.8), a missing q is 1.
+// Also used for Accept-Encoding by the compression middleware
+pub(crate) fn quality(item: &str) -> (String, f32) {
+    let mut parts = item.split(';').map(|part| part.trim());
+    let value = parts.next().unwrap_or("").to_lowercase();
+    let q = parts
+        .filter_map(|param| {
+            let mut pair = param.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some("q"), Some(q)) => q.trim().parse().ok(),
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or(1.0);
+    (value, q)
+}
+
+// Best first, q=0 means "not this one".
+// errors.rs reads `Accept` with it too, so both agree on the format
+pub(crate) fn preferences(header: &str) -> Vec<String> {
+    let mut items: Vec<(String, f32)> = header
+        .split(',')
+        .map(quality)
+        .filter(|&(ref value, q)| !value.is_empty() && q > 0.0)
+        .collect();
+    // stable, so equal q keep the order the client sent
+    items.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    items.into_iter().map(|(value, _)| value).collect()
+}
+
+fn from_name(format: &str) -> Option<Format> {
+    match format {
+        "text" | "txt" | "plain" => Some(Format::Text),
+        "json" => Some(Format::Json),
+        "html" => Some(Format::Html),
+        _ => None,
+    }
+}
+
+fn from_media_type(media_type: &str) -> Option<Format> {
+    match media_type {
+        "text/plain" | "text/*" | "*/*" => Some(Format::Text),
+        "application/json" | "application/*" => Some(Format::Json),
+        "text/html" => Some(Format::Html),
+        _ => None,
+    }
+}
+
+pub fn negotiate(query: Option<&str>, accept: Option<&str>) -> Option<Format> {
+    if let Some(name) = query {
+        return from_name(&name.to_lowercase());
+    }
+    match accept {
+        None => Some(Format::Text),
+        Some(accept) if accept.trim().is_empty() => Some(Format::Text),
+        Some(accept) => preferences(accept).iter().filter_map(|media_type| from_media_type(media_type)).next(),
+    }
+}
+
+// (language, greeting)
+pub fn language(accept_language: Option<&str>) -> (&'static str, &'static str) {
+    let accepted = preferences(accept_language.unwrap_or(""));
+    for tag in &accepted {
+        // `pt-BR` is `pt`
+        let primary = tag.split('-').next().unwrap_or("");
+        if let Some(&greeting) = GREETINGS.iter().find(|&&(language, _)| language == primary) {
+            return greeting;
+        }
+    }
+    GREETINGS[0]
+}
+
+#[derive(Serialize)]
+struct Greeting<'a> {
+    id: u32,
+    name: &'a str,
+    language: &'a str,
+    greeting: String,
+}
+
+fn header_value<'a, S>(req: &'a HttpRequest<S>, name: header::HeaderName) -> Option<&'a str> {
+    req.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+pub fn respond<S>(req: &HttpRequest<S>, user: &UserPath) -> Result<HttpResponse, Error> {
+    let query = req.query().get("format").cloned();
+    let format = match negotiate(query.as_ref().map(|s| s.as_str()), header_value(req, header::ACCEPT)) {
+        None => {
+            return Err(Error::new(
+                req,
+                StatusCode::NOT_ACCEPTABLE,
+                String::from("the greeting is available as text/plain, application/json or text/html"),
+            ))
+        }
+        Some(format) => format,
+    };
+    let (language, hello) = language(header_value(req, header::ACCEPT_LANGUAGE));
+    let greeting = format!("{} {}!", hello, user.name);
+
+    let mut response = HttpResponse::Ok();
+    response
+        .header(header::CONTENT_LANGUAGE, language)
+        .header(header::VARY, "Accept, Accept-Language");
+    Ok(match format {
+        Format::Text => response
+            .content_type("text/plain; charset=utf-8")
+            .body(format!("{} id:{}", greeting, user.id)),
+        Format::Json => response.json(Greeting { id: user.id, name: &user.name, language, greeting }),
+        Format::Html => response.content_type("text/html; charset=utf-8").body(format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<head><title>{}</title></head>\n\
+             <body>\n<h1>{}</h1>\n<p>id: {}</p>\n</body>\n</html>\n",
+            language,
+            escape(&greeting),
+            escape(&greeting),
+            user.id
+        )),
+    })
+}
+
+
+#[test]
+fn test_negotiate() {
+  assert_eq!(negotiate(None, None), Some(Format::Text));
+  assert_eq!(negotiate(None, Some("*/*")), Some(Format::Text));
+  assert_eq!(negotiate(None, Some("application/json")), Some(Format::Json));
+  assert_eq!(negotiate(None, Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")), Some(Format::Html));
+  assert_eq!(negotiate(None, Some("text/html;q=0.5, application/json")), Some(Format::Json));
+  assert_eq!(negotiate(None, Some("image/png, application/*;q=0.1")), Some(Format::Json));
+  assert_eq!(negotiate(None, Some("image/png")), None);
+  assert_eq!(negotiate(None, Some("text/plain;q=0, image/png")), None);
+}
+
+#[test]
+fn test_format_query_wins() {
+  assert_eq!(negotiate(Some("json"), Some("text/html")), Some(Format::Json));
+  assert_eq!(negotiate(Some("HTML"), None), Some(Format::Html));
+  assert_eq!(negotiate(Some("xml"), Some("*/*")), None);
+}
+
+#[test]
+fn test_language() {
+  assert_eq!(language(None), ("en", "Hello"));
+  assert_eq!(language(Some("pt-BR,pt;q=0.9,en;q=0.8")), ("pt", "Olá"));
+  assert_eq!(language(Some("ja, fr;q=0.5")), ("fr", "Bonjour"));
+  assert_eq!(language(Some("de;q=0.2, es;q=0.7")), ("es", "Hola"));
+  assert_eq!(language(Some("ja")), ("en", "Hello"));
+}
@@ -0,0 +1,38 @@
+// Probes for the orchestrator (docker, kubernetes, a load balancer)
+//
+//   /healthz  the process answers, restart it when this fails
+//   /readyz   it can take traffic: the storage answers and it is not
+//             shutting down, stop sending requests when this fails
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+use std::sync::atomic::Ordering;
+use users::AppState;
+
+
+#[derive(Serialize)]
+struct Probe<'a> {
+    status: &'a str,
+}
+
+fn probe(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(Probe { status: message })
+}
+
+pub fn healthz(_req: HttpRequest<AppState>) -> HttpResponse {
+    probe(StatusCode::OK, "ok")
+}
+
+pub fn readyz(req: HttpRequest<AppState>) -> HttpResponse {
+    let state = req.state();
+    if !state.ready.load(Ordering::SeqCst) {
+        return probe(StatusCode::SERVICE_UNAVAILABLE, "shutting down");
+    }
+    match state.store.ping() {
+        Err(e) => {
+            eprintln!("{}", e);
+            probe(StatusCode::SERVICE_UNAVAILABLE, "storage unavailable")
+        }
+        Ok(()) => probe(StatusCode::OK, "ready"),
+    }
+}
@@ -1,20 +1,150 @@
+extern crate actix;
 extern crate actix_web;
-use actix_web::{http, server, App, Path};
+extern crate bytes;
+extern crate futures;
+extern crate percent_encoding;
+extern crate rusqlite;
+extern crate serde;
+#[cfg_attr(test, macro_use)]
+extern crate serde_json;
+extern crate toml;
+#[macro_use]
+extern crate serde_derive;
 
-fn index(info: Path<(u32, String)>) -> String {
-    format!("Hello {}! id:{}", info.1, info.0)
+mod chat;
+mod config;
+mod errors;
+mod files;
+mod greeting;
+mod health;
+mod middleware;
+mod shutdown;
+mod store;
+mod user_path;
+mod users;
+#[cfg(test)]
+mod tests;
+
+use actix::prelude::*;
+use actix_web::http::Method;
+use actix_web::{server, App, HttpRequest, HttpResponse};
+use config::{Config, MiddlewareConfig};
+use errors::Error;
+use files::Files;
+use shutdown::Shutdown;
+use std::process;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use user_path::UserPath;
+use users::AppState;
+
+// An invalid id or name never reaches the handler,
+// see user_path.rs for the rules and errors.rs for the error bodies.
+// The format and the language of the greeting are in greeting.rs
+fn index((user, req): (UserPath, HttpRequest<AppState>)) -> Result<HttpResponse, Error> {
+    greeting::respond(&req, &user)
+}
+
+// Everything no route matched: a static file or 404
+fn fallback(req: HttpRequest<AppState>) -> HttpResponse {
+    let files = req.state().files.clone();
+    match files {
+        Some(files) => files.serve(&req),
+        None => errors::not_found(req),
+    }
+}
+
+fn app(state: AppState, config: &MiddlewareConfig) -> App<AppState> {
+    let mut app = App::with_state(state);
+    if config.trace {
+        app = app.middleware(middleware::Trace);
+    }
+    if !config.cors_origins.is_empty() {
+        app = app.middleware(middleware::Cors::new(&config.cors_origins));
+    }
+    app.middleware(middleware::Compression::new(&config.compression))
+        .resource("/healthz", |r| r.f(health::healthz))
+        .resource("/readyz", |r| r.f(health::readyz))
+        .resource("/{id}/{name}/index.html", |r| r.method(Method::GET).with(index))
+        .resource("/ws/{name}", |r| r.method(Method::GET).f(chat::connect))
+        .resource("/users", |r| {
+            r.method(Method::GET).with(users::list);
+            // the Json extractor answers a bad body with our JSON errors
+            r.method(Method::POST).with(users::create).1.error_handler(users::json_error);
+        })
+        .resource("/users/{id}", |r| {
+            r.method(Method::GET).with(users::read);
+            r.method(Method::PUT).with(users::update).2.error_handler(users::json_error);
+            r.method(Method::DELETE).with(users::delete);
+        })
+        .default_resource(|r| r.f(fallback))
 }
 
 // http://localhost:8080/50/bruno/index.html
 fn main() {
-    server::new(
-        || App::new()
-            .route("/{id}/{name}/index.html", http::Method::GET, index))
-        .bind("localhost:8080").unwrap()
-        .run();
+    let config = match Config::load() {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(config) => config,
+    };
+    let store = match store::open(&config.storage) {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(store) => store,
+    };
+
+    let files = Files::new(&config.static_files).map(Arc::new);
+    let ready = Arc::new(AtomicBool::new(true));
+    let state = AppState { store, files, ready: ready.clone() };
+    let middleware = config.middleware;
+
+    let system = System::new("actix-web-one");
+
+    let mut server = server::new(move || app(state.clone(), &middleware))
+        .shutdown_timeout(config.server.shutdown_timeout)
+        // SIGINT would stop without waiting, shutdown.rs handles the signals
+        .disable_signals()
+        // and stop the system once the server is done
+        .system_exit();
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    let server = match server.bind(&config.server.address) {
+        Err(e) => {
+            eprintln!("Can not listen on {}: {}", config.server.address, e);
+            process::exit(1);
+        }
+        Ok(server) => server,
+    };
+    let server: Addr<Syn, _> = server.start();
+
+    let _: Addr<Unsync, _> = Shutdown::new(server.recipient(), ready).start();
+
+    println!("Serving on http://{}", config.server.address);
+    system.run();
 }
 
 // Hello bruno! id:50
+// curl -H 'Accept-Language: pt-BR' 'http://localhost:8080/50/bruno/index.html?format=json'
+// {"id":50,"name":"bruno","language":"pt","greeting":"Olá bruno!"}
+// http://localhost:8080/abc/bruno/index.html
+// 400 Bad Request: id must be a number between 1 and 1000000, got "abc"
+// curl -H 'Accept: application/json' http://localhost:8080/0/bruno/index.html
+// {"status":400,"error":"Bad Request","message":"id must be between 1 and 1000000, got 0"}
+//
+// curl -d '{"name": "bruno"}' -H 'Content-Type: application/json' http://localhost:8080/users
+// {"id":1,"name":"bruno"}
+// curl http://localhost:8080/users
+// {"users":[{"id":1,"name":"bruno"}],"page":1,"per_page":20,"total":1}
 
 
+// APP_CONFIG=wasm-greet.toml cargo run   http://localhost:8080/index.html
+// APP_CONFIG=sqlite.toml cargo run   keeps the users in a file, see config.rs
+// websocat ws://localhost:8080/ws/bruno   chat with every other client, see chat.rs
+// curl http://localhost:8080/readyz
+// {"status":"ready"}
 // cargo watch -x run
@@ -0,0 +1,276 @@
+// Middleware around every route, each one toggled in the config file:
+//
+// [middleware]
+// trace = true                                # JSON access log + X-Request-Id
+// cors_origins = ["http://localhost:3000"]    # "*" for any, empty for no CORS
+// compression = ["br", "gzip"]                # by preference, empty for none
+//
+// `app()` registers them in this order, so Trace sees every response,
+// even the preflight answered by Cors.
+
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::{ContentEncoding, HeaderMap, Method, StatusCode};
+use actix_web::middleware::{Finished, Middleware, Response, Started};
+use actix_web::{HttpRequest, HttpResponse, Result};
+use config::Encoding;
+use greeting::quality;
+use serde_json;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+
+const REQUEST_ID: &str = "x-request-id";
+
+fn header_str<'a, S>(req: &'a HttpRequest<S>, name: HeaderName) -> Option<&'a str> {
+    req.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+
+// --- Request trace ---
+
+// workers have an App each, the counter is shared by all of them
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct TraceStart {
+    id: String,
+    instant: Instant,
+}
+
+#[derive(Serialize)]
+struct TraceLine<'a> {
+    request_id: &'a str,
+    remote_addr: Option<&'a str>,
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+    duration_ms: f64,
+}
+
+pub struct Trace;
+
+impl Trace {
+    // An id sent by a proxy is kept if it looks sane
+    fn incoming<S>(req: &HttpRequest<S>) -> Option<String> {
+        let id = header_str(req, HeaderName::from_static(REQUEST_ID))?;
+        let valid = !id.is_empty() && id.len() <= 64
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Some(id.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl<S> Middleware<S> for Trace {
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        let id = Trace::incoming(req).unwrap_or_else(|| {
+            format!("{:x}-{}", process::id(), NEXT_ID.fetch_add(1, Ordering::SeqCst))
+        });
+        req.extensions_mut().insert(TraceStart { id, instant: Instant::now() });
+        Ok(Started::Done)
+    }
+
+    fn response(&self, req: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        if let Some(start) = req.extensions().get::<TraceStart>() {
+            if let Ok(value) = HeaderValue::from_str(&start.id) {
+                resp.headers_mut().insert(HeaderName::from_static(REQUEST_ID), value);
+            }
+        }
+        Ok(Response::Done(resp))
+    }
+
+    // after the body is sent, so the duration is the whole request
+    fn finish(&self, req: &mut HttpRequest<S>, resp: &HttpResponse) -> Finished {
+        if let Some(start) = req.extensions().get::<TraceStart>() {
+            let elapsed = start.instant.elapsed();
+            let line = TraceLine {
+                request_id: &start.id,
+                remote_addr: req.connection_info().remote(),
+                method: req.method().as_str(),
+                path: req.path(),
+                status: resp.status().as_u16(),
+                duration_ms: elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1e6,
+            };
+            println!("{}", serde_json::to_string(&line).unwrap());
+        }
+        Finished::Done
+    }
+}
+
+
+// --- CORS ---
+
+pub struct Cors {
+    origins: Vec<String>,
+}
+
+impl Cors {
+    pub fn new(origins: &[String]) -> Cors {
+        Cors { origins: origins.to_vec() }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.origins.iter().any(|allowed| allowed == "*" || allowed == origin)
+    }
+
+    fn allowed_origin<S>(&self, req: &HttpRequest<S>) -> Option<HeaderValue> {
+        let origin = header_str(req, header::ORIGIN)?;
+        if self.allows(origin) {
+            HeaderValue::from_str(origin).ok()
+        } else {
+            None
+        }
+    }
+}
+
+impl<S> Middleware<S> for Cors {
+    // A preflight is answered here, it never reaches a route
+    fn start(&self, req: &mut HttpRequest<S>) -> Result<Started> {
+        let preflight = *req.method() == Method::OPTIONS
+            && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        if !preflight {
+            return Ok(Started::Done);
+        }
+        let origin = match self.allowed_origin(req) {
+            None => return Ok(Started::Response(HttpResponse::Forbidden().finish())),
+            Some(origin) => origin,
+        };
+        let headers = header_str(req, header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .unwrap_or("Content-Type")
+            .to_string();
+        Ok(Started::Response(
+            HttpResponse::build(StatusCode::NO_CONTENT)
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST, PUT, DELETE")
+                .header(header::ACCESS_CONTROL_ALLOW_HEADERS, headers)
+                .header(header::ACCESS_CONTROL_MAX_AGE, "3600")
+                .header(header::VARY, "Origin")
+                .finish(),
+        ))
+    }
+
+    fn response(&self, req: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        if let Some(origin) = self.allowed_origin(req) {
+            let headers = resp.headers_mut();
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static("X-Request-Id"));
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        Ok(Response::Done(resp))
+    }
+}
+
+
+// --- Compression ---
+
+// actix-web compresses the body itself, this only decides with what
+pub struct Compression {
+    encodings: Vec<Encoding>,
+}
+
+impl Compression {
+    pub fn new(encodings: &[Encoding]) -> Compression {
+        Compression { encodings: encodings.to_vec() }
+    }
+
+    // The first configured encoding the client accepts
+    fn choose(&self, accept_encoding: &str) -> ContentEncoding {
+        let accepted: Vec<(String, f32)> = accept_encoding.split(',').map(quality).collect();
+        let accepts = |name: &str| accepted.iter().any(|&(ref token, q)| (token == name || token == "*") && q > 0.0);
+        self.encodings
+            .iter()
+            .find(|encoding| accepts(encoding.name()))
+            .map(|encoding| match *encoding {
+                Encoding::Br => ContentEncoding::Br,
+                Encoding::Gzip => ContentEncoding::Gzip,
+                Encoding::Deflate => ContentEncoding::Deflate,
+            })
+            .unwrap_or(ContentEncoding::Identity)
+    }
+}
+
+// The encoded bytes are not the bytes of the identity ETag
+fn weaken_etag(headers: &mut HeaderMap) {
+    let weak = match headers.get(header::ETAG).and_then(|value| value.to_str().ok()) {
+        Some(etag) if !etag.starts_with("W/") => format!("W/{}", etag),
+        _ => return,
+    };
+    if let Ok(value) = HeaderValue::from_str(&weak) {
+        headers.insert(header::ETAG, value);
+    }
+}
+
+impl<S> Middleware<S> for Compression {
+    fn response(&self, req: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        // a handler that picked an encoding knows better,
+        // and a WebSocket handshake has no body to compress
+        if resp.content_encoding().is_some() || resp.status() == StatusCode::SWITCHING_PROTOCOLS {
+            return Ok(Response::Done(resp));
+        }
+        // Content-Range counts the bytes of the file, not of an encoded body
+        let status = resp.status();
+        if status == StatusCode::PARTIAL_CONTENT || status == StatusCode::RANGE_NOT_SATISFIABLE {
+            resp.set_content_encoding(ContentEncoding::Identity);
+            return Ok(Response::Done(resp));
+        }
+
+        let encoding = self.choose(header_str(req, header::ACCEPT_ENCODING).unwrap_or(""));
+        if !self.encodings.is_empty() {
+            // caches keep one copy per Accept-Encoding
+            resp.headers_mut().append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+        }
+        if encoding != ContentEncoding::Identity {
+            weaken_etag(resp.headers_mut());
+        }
+        // a 304 has no body, only the ETag of the one the cache has
+        if status == StatusCode::NOT_MODIFIED {
+            resp.set_content_encoding(ContentEncoding::Identity);
+        } else {
+            resp.set_content_encoding(encoding);
+        }
+        Ok(Response::Done(resp))
+    }
+}
+
+
+#[test]
+fn test_choose_encoding() {
+  let both = Compression::new(&[Encoding::Br, Encoding::Gzip]);
+  assert_eq!(both.choose("gzip, deflate, br"), ContentEncoding::Br);
+  assert_eq!(both.choose("gzip, deflate"), ContentEncoding::Gzip);
+  assert_eq!(both.choose("br;q=0, gzip"), ContentEncoding::Gzip);
+  assert_eq!(both.choose("*"), ContentEncoding::Br);
+  assert_eq!(both.choose(""), ContentEncoding::Identity);
+  assert_eq!(Compression::new(&[]).choose("gzip, br"), ContentEncoding::Identity);
+}
+
+#[test]
+fn test_weaken_etag() {
+  let mut headers = HeaderMap::new();
+  headers.insert(header::ETAG, HeaderValue::from_static("\"8-5b0\""));
+  weaken_etag(&mut headers);
+  assert_eq!(headers[header::ETAG], "W/\"8-5b0\"");
+  weaken_etag(&mut headers);
+  assert_eq!(headers[header::ETAG], "W/\"8-5b0\"");
+}
+
+#[test]
+fn test_cors_origins() {
+  let cors = Cors::new(&[String::from("http://localhost:3000")]);
+  assert!(cors.allows("http://localhost:3000"));
+  assert!(!cors.allows("http://localhost:3001"));
+  assert!(!Cors::new(&[]).allows("http://localhost:3000"));
+  assert!(Cors::new(&[String::from("*")]).allows("https://example.com"));
+}
+
+#[test]
+fn test_trace_keeps_a_sane_request_id() {
+  use actix_web::test::TestRequest;
+
+  let req = TestRequest::with_header(REQUEST_ID, "abc-123").finish();
+  assert_eq!(Trace::incoming(&req), Some(String::from("abc-123")));
+  let req = TestRequest::with_header(REQUEST_ID, "abc 123; rm -rf").finish();
+  assert_eq!(Trace::incoming(&req), None);
+}
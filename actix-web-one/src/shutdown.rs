@@ -0,0 +1,51 @@
+// Graceful shutdown on SIGINT (Ctrl-C) and SIGTERM (docker stop)
+//
+// The server stops accepting connections, /readyz starts answering 503
+// and the requests in flight get `shutdown_timeout` seconds to finish.
+// Then the server stops the actix system and `main` returns.
+
+use actix::actors::signal::{ProcessSignals, Signal, SignalType, Subscribe};
+use actix::prelude::*;
+use actix_web::server::StopServer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+
+pub struct Shutdown {
+    server: Recipient<Syn, StopServer>,
+    // shared with AppState for /readyz
+    ready: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn new(server: Recipient<Syn, StopServer>, ready: Arc<AtomicBool>) -> Shutdown {
+        Shutdown { server, ready }
+    }
+}
+
+impl Actor for Shutdown {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let signals = Arbiter::system_registry().get::<ProcessSignals>();
+        let me: Addr<Syn, _> = ctx.address();
+        signals.do_send(Subscribe(me.recipient()));
+    }
+}
+
+impl Handler<Signal> for Shutdown {
+    type Result = ();
+
+    fn handle(&mut self, signal: Signal, _: &mut Context<Self>) {
+        match signal.0 {
+            SignalType::Int | SignalType::Term | SignalType::Quit => {
+                // a second Ctrl-C while draining does nothing more
+                if self.ready.swap(false, Ordering::SeqCst) {
+                    println!("Received {:?}, finishing the requests in flight", signal.0);
+                    let _ = self.server.do_send(StopServer { graceful: true });
+                }
+            }
+            _ => (),
+        }
+    }
+}
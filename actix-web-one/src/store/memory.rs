@@ -0,0 +1,59 @@
+use super::{StoreError, User, UserStore};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use user_path::{MAX_ID, MIN_ID};
+
+
+struct Users {
+    by_id: BTreeMap<u32, User>,
+    next_id: u32,
+}
+
+pub struct MemoryStore {
+    users: Mutex<Users>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore { users: Mutex::new(Users { by_id: BTreeMap::new(), next_id: MIN_ID }) }
+    }
+}
+
+impl UserStore for MemoryStore {
+    fn list(&self, offset: usize, limit: usize) -> Result<(Vec<User>, usize), StoreError> {
+        let users = self.users.lock().unwrap();
+        let page = users.by_id.values().skip(offset).take(limit).cloned().collect();
+        Ok((page, users.by_id.len()))
+    }
+
+    fn create(&self, name: String) -> Result<Option<User>, StoreError> {
+        let mut users = self.users.lock().unwrap();
+        if users.next_id > MAX_ID {
+            return Ok(None);
+        }
+        let user = User { id: users.next_id, name };
+        users.next_id += 1;
+        users.by_id.insert(user.id, user.clone());
+        Ok(Some(user))
+    }
+
+    fn get(&self, id: u32) -> Result<Option<User>, StoreError> {
+        Ok(self.users.lock().unwrap().by_id.get(&id).cloned())
+    }
+
+    fn update(&self, id: u32, name: String) -> Result<Option<User>, StoreError> {
+        let mut users = self.users.lock().unwrap();
+        Ok(users.by_id.get_mut(&id).map(|user| {
+            user.name = name;
+            user.clone()
+        }))
+    }
+
+    fn delete(&self, id: u32) -> Result<bool, StoreError> {
+        Ok(self.users.lock().unwrap().by_id.remove(&id).is_some())
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+// Where the users are kept
+//
+// `UserStore` is what the handlers use, the backend is chosen in the
+// config file (see config.rs):
+//
+//   memory  lost when the server stops, the default
+//   sqlite  a single file, created with its schema on the first start
+
+use config::StorageConfig;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+mod memory;
+mod sqlite;
+
+pub use self::memory::MemoryStore;
+pub use self::sqlite::SqliteStore;
+
+
+// `id` is the key, `name` can change, see users.rs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: u32,
+    pub name: String,
+}
+
+// Something went wrong in the backend, not in the request
+#[derive(Debug)]
+pub struct StoreError(pub String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "storage error: {}", self.0)
+    }
+}
+
+impl error::Error for StoreError {
+    fn description(&self) -> &str {
+        &self.0
+    }
+}
+
+// Shared by all the server workers, so it must be Send + Sync
+pub trait UserStore: Send + Sync {
+    // One page of users ordered by id, and the total number of users
+    fn list(&self, offset: usize, limit: usize) -> Result<(Vec<User>, usize), StoreError>;
+    // Ok(None) when every id is taken, ids are never reused
+    fn create(&self, name: String) -> Result<Option<User>, StoreError>;
+    fn get(&self, id: u32) -> Result<Option<User>, StoreError>;
+    fn update(&self, id: u32, name: String) -> Result<Option<User>, StoreError>;
+    // Ok(false) when there was no such user
+    fn delete(&self, id: u32) -> Result<bool, StoreError>;
+    // Err when the backend can not be used, for /readyz
+    fn ping(&self) -> Result<(), StoreError>;
+}
+
+pub fn open(config: &StorageConfig) -> Result<Arc<UserStore>, StoreError> {
+    match *config {
+        StorageConfig::Memory => Ok(Arc::new(MemoryStore::new())),
+        StorageConfig::Sqlite { ref path } => Ok(Arc::new(SqliteStore::open(path)?)),
+    }
+}
+
+
+// The same checks for every backend
+#[cfg(test)]
+fn check_store(store: &UserStore) {
+    let bruno = store.create(String::from("bruno")).unwrap().unwrap();
+    let ana = store.create(String::from("ana")).unwrap().unwrap();
+    assert_eq!((bruno.id, ana.id), (1, 2));
+
+    assert_eq!(store.get(1).unwrap(), Some(bruno));
+    assert_eq!(store.update(2, String::from("anna")).unwrap().unwrap().name, "anna");
+    assert_eq!(store.get(2).unwrap().unwrap().name, "anna");
+    assert_eq!(store.update(3, String::from("nobody")).unwrap(), None);
+
+    assert!(store.delete(1).unwrap());
+    assert!(!store.delete(1).unwrap());
+    assert_eq!(store.get(1).unwrap(), None);
+    assert_eq!(store.create(String::from("carla")).unwrap().unwrap().id, 3);
+
+    for i in 0..23 {
+        store.create(format!("user{}", i)).unwrap();
+    }
+    let (page, total) = store.list(20, 20).unwrap();
+    assert_eq!(total, 25);
+    assert_eq!(page.len(), 5);
+    assert_eq!(page[0].id, 22);
+    assert_eq!(store.list(100, 20).unwrap(), (vec![], 25));
+}
+
+#[test]
+fn test_ping() {
+  assert!(MemoryStore::new().ping().is_ok());
+  assert!(SqliteStore::in_memory().unwrap().ping().is_ok());
+}
+
+#[test]
+fn test_memory_store() {
+  check_store(&MemoryStore::new());
+}
+
+#[test]
+fn test_sqlite_store() {
+  check_store(&SqliteStore::in_memory().unwrap());
+}
@@ -0,0 +1,133 @@
+// SQLite is linked into the binary (the `bundled` feature),
+// there is no database server to install or start.
+
+use super::{StoreError, User, UserStore};
+use rusqlite::{self, Connection};
+use std::path::Path;
+use std::sync::Mutex;
+use user_path::MAX_ID;
+
+
+// AUTOINCREMENT so the id of a deleted user is never given to a new one
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        id   INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL
+    )";
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> StoreError {
+        StoreError(e.to_string())
+    }
+}
+
+// A Connection can not be used by two threads at once,
+// the workers take turns
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    // The file and the table are created when missing
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore, StoreError> {
+        SqliteStore::with_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<SqliteStore, StoreError> {
+        SqliteStore::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<SqliteStore, StoreError> {
+        connection.execute(SCHEMA, &[])?;
+        Ok(SqliteStore { connection: Mutex::new(connection) })
+    }
+}
+
+fn user(id: i64, name: String) -> User {
+    User { id: id as u32, name }
+}
+
+impl UserStore for SqliteStore {
+    fn list(&self, offset: usize, limit: usize) -> Result<(Vec<User>, usize), StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let total: i64 = connection.query_row("SELECT COUNT(*) FROM users", &[], |row| row.get(0))?;
+
+        let mut statement = connection.prepare("SELECT id, name FROM users ORDER BY id LIMIT ? OFFSET ?")?;
+        let rows = statement.query_map(&[&(limit as i64), &(offset as i64)], |row| user(row.get(0), row.get(1)))?;
+        let mut users = Vec::new();
+        for row in rows {
+            users.push(row?);
+        }
+        Ok((users, total as usize))
+    }
+
+    fn create(&self, name: String) -> Result<Option<User>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("INSERT INTO users (name) VALUES (?)", &[&name])?;
+        let id = transaction.last_insert_rowid();
+        if id > MAX_ID as i64 {
+            // dropping the transaction rolls the insert back
+            return Ok(None);
+        }
+        transaction.commit()?;
+        Ok(Some(user(id, name)))
+    }
+
+    fn get(&self, id: u32) -> Result<Option<User>, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let found = connection.query_row("SELECT id, name FROM users WHERE id = ?", &[&(id as i64)], |row| {
+            user(row.get(0), row.get(1))
+        });
+        match found {
+            Ok(user) => Ok(Some(user)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn update(&self, id: u32, name: String) -> Result<Option<User>, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let changed = connection.execute("UPDATE users SET name = ? WHERE id = ?", &[&name, &(id as i64)])?;
+        if changed == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(user(id as i64, name)))
+        }
+    }
+
+    fn delete(&self, id: u32) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let deleted = connection.execute("DELETE FROM users WHERE id = ?", &[&(id as i64)])?;
+        Ok(deleted > 0)
+    }
+
+    fn ping(&self) -> Result<(), StoreError> {
+        let connection = self.connection.lock().unwrap();
+        connection.query_row("SELECT COUNT(*) FROM users", &[], |_| ())?;
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_data_survives_a_restart() {
+  use std::env;
+  use std::fs;
+
+  let path = env::temp_dir().join(format!("actix-web-one-test-{}.db", ::std::process::id()));
+  {
+      let store = SqliteStore::open(&path).unwrap();
+      store.create(String::from("bruno")).unwrap();
+      store.create(String::from("ana")).unwrap();
+      store.delete(2).unwrap();
+  }
+  {
+      let store = SqliteStore::open(&path).unwrap();
+      assert_eq!(store.get(1).unwrap().unwrap().name, "bruno");
+      // the deleted id is not reused after a restart either
+      assert_eq!(store.create(String::from("carla")).unwrap().unwrap().id, 3);
+  }
+  fs::remove_file(&path).unwrap();
+}
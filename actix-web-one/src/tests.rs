@@ -0,0 +1,504 @@
+// Integration tests: a real server on a random local port,
+// the requests are sent with the actix-web client.
+
+use actix_web::client::{ClientRequest, ClientResponse};
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::TestServer;
+use actix_web::{ws, HttpMessage};
+use config::{Encoding, MiddlewareConfig, StaticConfig};
+use files::Files;
+use serde_json::{self, Value};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use store::MemoryStore;
+use users::AppState;
+
+
+fn configured_server(files: Option<Files>, middleware: MiddlewareConfig) -> TestServer {
+    let state = AppState {
+        store: Arc::new(MemoryStore::new()),
+        files: files.map(Arc::new),
+        ready: Arc::new(AtomicBool::new(true)),
+    };
+    TestServer::with_factory(move || super::app(state.clone(), &middleware))
+}
+
+fn server() -> TestServer {
+    configured_server(None, MiddlewareConfig::default())
+}
+
+fn send(srv: &mut TestServer, request: ClientRequest) -> (ClientResponse, Vec<u8>) {
+    let response = srv.execute(request.send()).unwrap();
+    let body = srv.execute(response.body()).unwrap();
+    (response, body.to_vec())
+}
+
+// status and JSON body, Null when the body is empty
+fn call(srv: &mut TestServer, method: Method, path: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = srv.client(method, path);
+    let request = match body {
+        Some(body) => request.json(body).unwrap(),
+        None => request.finish().unwrap(),
+    };
+    let (response, body) = send(srv, request);
+    let json = if body.is_empty() { Value::Null } else { serde_json::from_slice(&body).unwrap() };
+    (response.status(), json)
+}
+
+
+#[test]
+fn test_index() {
+  let mut srv = server();
+
+  let request = srv.get().uri(srv.url("/50/bruno/index.html")).finish().unwrap();
+  let (response, body) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body, b"Hello bruno! id:50".to_vec());
+
+  let request = srv.get().uri(srv.url("/abc/bruno/index.html"))
+      .header("Accept", "application/json")
+      .finish().unwrap();
+  let (response, body) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  let error: Value = serde_json::from_slice(&body).unwrap();
+  assert_eq!(error["status"], 400);
+  assert_eq!(error["message"], "id must be a number between 1 and 1000000, got \"abc\"");
+
+  let request = srv.get().uri(srv.url("/50/bruno")).finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::NOT_FOUND);
+  assert_eq!(response.headers().get("content-type").unwrap(), "text/html; charset=utf-8");
+}
+
+#[test]
+fn test_users_crud() {
+  let mut srv = server();
+
+  let request = srv.client(Method::POST, "/users").json(json!({"name": "bruno"})).unwrap();
+  let (response, body) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::CREATED);
+  assert_eq!(response.headers().get("location").unwrap(), "/users/1");
+  assert_eq!(serde_json::from_slice::<Value>(&body).unwrap(), json!({"id": 1, "name": "bruno"}));
+
+  assert_eq!(call(&mut srv, Method::GET, "/users/1", None), (StatusCode::OK, json!({"id": 1, "name": "bruno"})));
+
+  let (status, user) = call(&mut srv, Method::PUT, "/users/1", Some(json!({"name": "rocha"})));
+  assert_eq!((status, user), (StatusCode::OK, json!({"id": 1, "name": "rocha"})));
+
+  assert_eq!(call(&mut srv, Method::DELETE, "/users/1", None), (StatusCode::NO_CONTENT, Value::Null));
+  let (status, error) = call(&mut srv, Method::GET, "/users/1", None);
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  assert_eq!(error["message"], "no user with id 1");
+  assert_eq!(call(&mut srv, Method::DELETE, "/users/1", None).0, StatusCode::NOT_FOUND);
+  assert_eq!(call(&mut srv, Method::PUT, "/users/1", Some(json!({"name": "x"}))).0, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_users_validation() {
+  let mut srv = server();
+
+  let (status, error) = call(&mut srv, Method::POST, "/users", Some(json!({"name": "<b>bruno</b>"})));
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  assert_eq!(error["error"], "Bad Request");
+  assert_eq!(call(&mut srv, Method::POST, "/users", Some(json!({"name": ""}))).0, StatusCode::BAD_REQUEST);
+  assert_eq!(call(&mut srv, Method::POST, "/users", Some(json!({"id": 7, "name": "bruno"}))).0, StatusCode::BAD_REQUEST);
+  assert_eq!(call(&mut srv, Method::POST, "/users", Some(json!({}))).0, StatusCode::BAD_REQUEST);
+  assert_eq!(call(&mut srv, Method::GET, "/users?per_page=1000", None).0, StatusCode::BAD_REQUEST);
+  assert_eq!(call(&mut srv, Method::GET, "/users?page=0", None).0, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_users_extractor_errors_are_json() {
+  let mut srv = server();
+
+  for method in &[Method::GET, Method::PUT, Method::DELETE] {
+      let body = if *method == Method::PUT { Some(json!({"name": "bruno"})) } else { None };
+      let (status, error) = call(&mut srv, method.clone(), "/users/abc", body);
+      assert_eq!(status, StatusCode::BAD_REQUEST, "{} /users/abc", method);
+      assert_eq!(error["status"], 400, "{} /users/abc", method);
+      assert_eq!(error["message"], "id must be a number, got \"abc\"", "{} /users/abc", method);
+  }
+
+  let bodies = [
+      ("application/json", "{\"name\": "),
+      ("application/json", "[1, 2]"),
+      ("text/plain", "{\"name\": \"bruno\"}"),
+  ];
+  for &(content_type, body) in bodies.iter() {
+      for &(ref method, path) in &[(Method::POST, "/users"), (Method::PUT, "/users/1")] {
+          let request = srv.client(method.clone(), path)
+              .header("Content-Type", content_type)
+              .body(body)
+              .unwrap();
+          let (response, bytes) = send(&mut srv, request);
+          assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{} {} {:?}", method, path, body);
+          assert_eq!(response.headers().get("content-type").unwrap(), "application/json", "{} {} {:?}", method, path, body);
+          let error: Value = serde_json::from_slice(&bytes).unwrap();
+          assert_eq!(error["error"], "Bad Request", "{} {} {:?}", method, path, body);
+      }
+  }
+}
+
+#[test]
+fn test_users_pagination() {
+  let mut srv = server();
+  for i in 0..25 {
+      let name = format!("user{}", i);
+      assert_eq!(call(&mut srv, Method::POST, "/users", Some(json!({ "name": name }))).0, StatusCode::CREATED);
+  }
+
+  let (status, list) = call(&mut srv, Method::GET, "/users", None);
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(list["users"].as_array().unwrap().len(), 20);
+  assert_eq!((list["page"].clone(), list["per_page"].clone(), list["total"].clone()), (json!(1), json!(20), json!(25)));
+
+  let (_, list) = call(&mut srv, Method::GET, "/users?page=3&per_page=10", None);
+  let ids: Vec<u64> = list["users"].as_array().unwrap().iter().map(|user| user["id"].as_u64().unwrap()).collect();
+  assert_eq!(ids, vec![21, 22, 23, 24, 25]);
+
+  let (_, list) = call(&mut srv, Method::GET, "/users?page=4&per_page=10", None);
+  assert_eq!(list["users"], json!([]));
+}
+
+#[test]
+fn test_index_negotiation() {
+  let mut srv = server();
+  let mut get = |path: &str, headers: &[(&str, &str)]| {
+      let mut request = srv.get();
+      request.uri(srv.url(path));
+      for &(name, value) in headers {
+          request.header(name, value);
+      }
+      let request = request.finish().unwrap();
+      let (response, body) = send(&mut srv, request);
+      let content_type = response.headers().get("content-type").unwrap().to_str().unwrap().to_string();
+      (response.status(), content_type, String::from_utf8(body).unwrap())
+  };
+
+  let (status, content_type, body) = get("/50/bruno/index.html", &[("Accept-Language", "pt-BR,pt;q=0.9")]);
+  assert_eq!((status, content_type.as_str(), body.as_str()), (StatusCode::OK, "text/plain; charset=utf-8", "Olá bruno! id:50"));
+
+  let (_, content_type, body) = get("/50/bruno/index.html", &[("Accept", "application/json")]);
+  assert_eq!(content_type, "application/json");
+  let greeting: Value = serde_json::from_str(&body).unwrap();
+  assert_eq!(greeting, json!({"id": 50, "name": "bruno", "language": "en", "greeting": "Hello bruno!"}));
+
+  let (_, content_type, body) = get("/50/bruno/index.html?format=html", &[("Accept", "application/json"), ("Accept-Language", "fr")]);
+  assert_eq!(content_type, "text/html; charset=utf-8");
+  assert!(body.contains("<h1>Bonjour bruno!</h1>"));
+
+  let (status, _, _) = get("/50/bruno/index.html", &[("Accept", "image/png")]);
+  assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+  let (status, _, _) = get("/50/bruno/index.html?format=xml", &[]);
+  assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+}
+
+// A throwaway site in the temp dir, removed when dropped
+struct Site {
+    dir: PathBuf,
+}
+
+impl Site {
+    fn new(name: &str) -> Site {
+        let dir = env::temp_dir().join(format!("actix-web-one-{}-{}", name, ::std::process::id()));
+        fs::create_dir_all(dir.join("pkg")).unwrap();
+        fs::write(dir.join("index.html"), "<h1>wasm-greet</h1>").unwrap();
+        fs::write(dir.join("index.js"), "import('./pkg/greet.js');").unwrap();
+        fs::write(dir.join("pkg").join("greet_bg.wasm"), b"\0asm\x01\0\0\0".to_vec()).unwrap();
+        Site { dir }
+    }
+
+    fn server(&self) -> TestServer {
+        let config = StaticConfig { dir: Some(self.dir.clone()), spa_fallback: true };
+        configured_server(Files::new(&config), MiddlewareConfig::default())
+    }
+}
+
+impl Drop for Site {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn test_static_files() {
+  let site = Site::new("static");
+  let mut srv = site.server();
+  let mut get = |path: &str, headers: &[(&str, &str)]| {
+      let mut request = srv.get();
+      request.uri(srv.url(path));
+      for &(name, value) in headers {
+          request.header(name, value);
+      }
+      let request = request.finish().unwrap();
+      send(&mut srv, request)
+  };
+
+  let (response, body) = get("/pkg/greet_bg.wasm", &[("Accept-Encoding", "identity")]);
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(response.headers().get("content-type").unwrap(), "application/wasm");
+  assert_eq!(body, b"\0asm\x01\0\0\0".to_vec());
+  let etag = response.headers().get("etag").unwrap().to_str().unwrap().to_string();
+  let last_modified = response.headers().get("last-modified").unwrap().to_str().unwrap().to_string();
+
+  let (response, _) = get("/index.js", &[]);
+  assert_eq!(response.headers().get("content-type").unwrap(), "application/javascript; charset=utf-8");
+
+  // the browser cache
+  let (response, body) = get("/pkg/greet_bg.wasm", &[("If-None-Match", etag.as_str())]);
+  assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+  assert!(body.is_empty());
+  let (response, _) = get("/pkg/greet_bg.wasm", &[("If-Modified-Since", last_modified.as_str())]);
+  assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+  let (response, _) = get("/pkg/greet_bg.wasm", &[("If-None-Match", "\"old\"")]);
+  assert_eq!(response.status(), StatusCode::OK);
+
+  let (response, body) = get("/pkg/greet_bg.wasm", &[("Range", "bytes=1-3")]);
+  assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+  assert_eq!(response.headers().get("content-range").unwrap(), "bytes 1-3/8");
+  assert_eq!(body, b"asm".to_vec());
+  let (response, _) = get("/pkg/greet_bg.wasm", &[("Range", "bytes=8-")]);
+  assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+  // the range is of the file bytes, it is never encoded
+  let (response, body) = get("/pkg/greet_bg.wasm", &[("Range", "bytes=1-3"), ("Accept-Encoding", "gzip")]);
+  assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+  assert!(!response.headers().contains_key("content-encoding"));
+  assert_eq!(response.headers().get("content-range").unwrap(), "bytes 1-3/8");
+  assert_eq!(body, b"asm".to_vec());
+  assert_eq!(response.headers().get("etag").unwrap().to_str().unwrap(), etag);
+
+  // the gzip copy has its own, weak, ETag and caches know it varies
+  let (response, body) = get("/pkg/greet_bg.wasm", &[("Accept-Encoding", "gzip")]);
+  assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+  assert!(response.headers().get_all("vary").iter().any(|vary| vary == "Accept-Encoding"));
+  assert_eq!(response.headers().get("etag").unwrap().to_str().unwrap(), format!("W/{}", etag));
+  assert_eq!(body, b"\0asm\x01\0\0\0".to_vec());
+  let weak = format!("W/{}", etag);
+  let (response, _) = get("/pkg/greet_bg.wasm", &[("Accept-Encoding", "gzip"), ("If-None-Match", weak.as_str())]);
+  assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+fn test_spa_fallback_and_precedence() {
+  let site = Site::new("spa");
+  let mut srv = site.server();
+
+  let request = srv.get().uri(srv.url("/some/app/route")).finish().unwrap();
+  let (response, body) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body, b"<h1>wasm-greet</h1>".to_vec());
+
+  let request = srv.get().uri(srv.url("/missing.js")).finish().unwrap();
+  assert_eq!(send(&mut srv, request).0.status(), StatusCode::NOT_FOUND);
+  let request = srv.get().uri(srv.url("/%2e%2e/Cargo.toml")).finish().unwrap();
+  assert_eq!(send(&mut srv, request).0.status(), StatusCode::NOT_FOUND);
+
+  // the routes of the app come first
+  let request = srv.get().uri(srv.url("/50/bruno/index.html")).finish().unwrap();
+  assert_eq!(send(&mut srv, request).1, b"Hello bruno! id:50".to_vec());
+  assert_eq!(call(&mut srv, Method::GET, "/users", None).0, StatusCode::OK);
+}
+
+#[test]
+fn test_request_id() {
+  let mut srv = server();
+
+  let request = srv.get().uri(srv.url("/50/bruno/index.html")).finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert!(response.headers().contains_key("x-request-id"));
+
+  let request = srv.get().uri(srv.url("/nope")).header("X-Request-Id", "from-proxy-1").finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::NOT_FOUND);
+  assert_eq!(response.headers().get("x-request-id").unwrap(), "from-proxy-1");
+}
+
+#[test]
+fn test_cors() {
+  let middleware = MiddlewareConfig {
+      cors_origins: vec![String::from("http://localhost:3000")],
+      ..MiddlewareConfig::default()
+  };
+  let mut srv = configured_server(None, middleware);
+
+  let request = srv.client(Method::OPTIONS, "/users")
+      .header("Origin", "http://localhost:3000")
+      .header("Access-Control-Request-Method", "POST")
+      .header("Access-Control-Request-Headers", "content-type")
+      .finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::NO_CONTENT);
+  assert_eq!(response.headers().get("access-control-allow-origin").unwrap(), "http://localhost:3000");
+  assert_eq!(response.headers().get("access-control-allow-headers").unwrap(), "content-type");
+
+  let request = srv.client(Method::OPTIONS, "/users")
+      .header("Origin", "http://evil.example")
+      .header("Access-Control-Request-Method", "DELETE")
+      .finish().unwrap();
+  assert_eq!(send(&mut srv, request).0.status(), StatusCode::FORBIDDEN);
+
+  let request = srv.get().uri(srv.url("/users")).header("Origin", "http://localhost:3000").finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert_eq!(response.headers().get("access-control-allow-origin").unwrap(), "http://localhost:3000");
+  let request = srv.get().uri(srv.url("/users")).header("Origin", "http://evil.example").finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert!(!response.headers().contains_key("access-control-allow-origin"));
+}
+
+#[test]
+fn test_compression() {
+  let encoding = |middleware: MiddlewareConfig, accept_encoding: &str| {
+      let mut srv = configured_server(None, middleware);
+      let request = srv.get().uri(srv.url("/users"))
+          .header("Accept-Encoding", accept_encoding)
+          .finish().unwrap();
+      let (response, body) = send(&mut srv, request);
+      // the client decodes the body
+      assert_eq!(serde_json::from_slice::<Value>(&body).unwrap()["total"], 0);
+      response.headers().get("content-encoding").map(|value| value.to_str().unwrap().to_string())
+  };
+
+  assert_eq!(encoding(MiddlewareConfig::default(), "gzip, br"), Some(String::from("br")));
+  assert_eq!(encoding(MiddlewareConfig::default(), "gzip"), Some(String::from("gzip")));
+  let gzip_only = MiddlewareConfig { compression: vec![Encoding::Gzip], ..MiddlewareConfig::default() };
+  assert_eq!(encoding(gzip_only, "br"), None);
+  let off = MiddlewareConfig { compression: vec![], ..MiddlewareConfig::default() };
+  assert_eq!(encoding(off, "gzip, br"), None);
+}
+
+#[test]
+fn test_health_probes() {
+  let ready = Arc::new(AtomicBool::new(true));
+  let state = AppState {
+      store: Arc::new(MemoryStore::new()),
+      files: None,
+      ready: ready.clone(),
+  };
+  let mut srv = TestServer::with_factory(move || super::app(state.clone(), &MiddlewareConfig::default()));
+
+  assert_eq!(call(&mut srv, Method::GET, "/healthz", None), (StatusCode::OK, json!({"status": "ok"})));
+  assert_eq!(call(&mut srv, Method::GET, "/readyz", None), (StatusCode::OK, json!({"status": "ready"})));
+
+  // what shutdown.rs does on SIGTERM
+  ready.store(false, Ordering::SeqCst);
+  assert_eq!(call(&mut srv, Method::GET, "/healthz", None).0, StatusCode::OK);
+  assert_eq!(
+      call(&mut srv, Method::GET, "/readyz", None),
+      (StatusCode::SERVICE_UNAVAILABLE, json!({"status": "shutting down"}))
+  );
+}
+
+#[test]
+fn test_graceful_shutdown_finishes_requests_in_flight() {
+  use actix::actors::signal::{Signal, SignalType};
+  use actix::prelude::*;
+  use actix_web::server;
+  use shutdown::Shutdown;
+  use std::io::{Read, Write};
+  use std::net::TcpStream;
+  use std::sync::mpsc;
+  use std::thread;
+  use std::time::Duration;
+
+  let ready = Arc::new(AtomicBool::new(true));
+  let (sender, receiver) = mpsc::channel();
+  let server_ready = ready.clone();
+  let server_thread = thread::spawn(move || {
+      let system = System::new("graceful-shutdown-test");
+      let state = AppState {
+          store: Arc::new(MemoryStore::new()),
+          files: None,
+          ready: server_ready.clone(),
+      };
+      let server = server::new(move || {
+          super::app(state.clone(), &MiddlewareConfig::default())
+              .resource("/slow", |r| r.f(|_| {
+                  thread::sleep(Duration::from_millis(500));
+                  "finished"
+              }))
+      })
+          .workers(1)
+          .shutdown_timeout(5)
+          .disable_signals()
+          .system_exit()
+          .bind("127.0.0.1:0")
+          .unwrap();
+      let address = server.addrs()[0];
+      let server: Addr<Syn, _> = server.start();
+      // as in main
+      let shutdown: Addr<Syn, _> = Shutdown::new(server.recipient(), server_ready).start();
+      sender.send((address, shutdown)).unwrap();
+      system.run();
+  });
+  let (address, shutdown) = receiver.recv().unwrap();
+
+  let mut stream = TcpStream::connect(address).unwrap();
+  stream.write_all(b"GET /slow HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+  // the request is being handled when the signal arrives,
+  // what ProcessSignals sends the Shutdown actor on SIGTERM
+  thread::sleep(Duration::from_millis(100));
+  shutdown.do_send(Signal(SignalType::Term));
+
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.0 200 OK"), "{}", response);
+  assert!(response.ends_with("finished"), "{}", response);
+  // /readyz said 503 while draining
+  assert!(!ready.load(Ordering::SeqCst));
+
+  // the system stops once the server is done, and nothing listens anymore
+  server_thread.join().unwrap();
+  assert!(TcpStream::connect(address).is_err());
+}
+
+#[test]
+fn test_chat() {
+  use futures::Stream;
+
+  let mut srv = server();
+
+  // the next text frame, pings are skipped
+  fn next(srv: &mut TestServer, reader: ws::ClientReader) -> (Value, ws::ClientReader) {
+      let mut reader = reader;
+      loop {
+          let (message, rest) = srv.execute(reader.into_future()).map_err(|(e, _)| e).unwrap();
+          reader = rest;
+          if let Some(ws::Message::Text(text)) = message {
+              return (serde_json::from_str(&text).unwrap(), reader);
+          }
+      }
+  }
+
+  let (bruno, mut bruno_writer) = srv.execute(ws::Client::new(srv.url("/ws/bruno")).connect()).unwrap();
+  let (event, bruno) = next(&mut srv, bruno);
+  assert_eq!(event, json!({"event": "join", "name": "bruno"}));
+
+  let (ana, mut ana_writer) = srv.execute(ws::Client::new(srv.url("/ws/ana")).connect()).unwrap();
+  let (event, ana) = next(&mut srv, ana);
+  assert_eq!(event, json!({"event": "join", "name": "ana"}));
+  let (event, bruno) = next(&mut srv, bruno);
+  assert_eq!(event, json!({"event": "join", "name": "ana"}));
+
+  // everybody gets the message, the sender too
+  ana_writer.text("  hello  ");
+  let (event, bruno) = next(&mut srv, bruno);
+  assert_eq!(event, json!({"event": "message", "name": "ana", "text": "hello"}));
+  let (event, _ana) = next(&mut srv, ana);
+  assert_eq!(event, json!({"event": "message", "name": "ana", "text": "hello"}));
+
+  ana_writer.close(None);
+  let (event, _bruno) = next(&mut srv, bruno);
+  assert_eq!(event, json!({"event": "leave", "name": "ana"}));
+  bruno_writer.close(None);
+
+  // the name follows the index route rules
+  let request = srv.get().uri(srv.url("/ws/bruno.rocha"))
+      .header("Upgrade", "websocket")
+      .finish().unwrap();
+  let (response, _) = send(&mut srv, request);
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
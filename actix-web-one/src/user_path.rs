@@ -0,0 +1,106 @@
+// The `{id}/{name}` part of the url, validated
+//
+// Using `UserPath` as a handler argument is all a route needs:
+// actix-web calls `from_request` before the handler and answers with
+// the `errors::Error` when the path is not valid.
+//
+//   /50/bruno/index.html      -> UserPath { id: 50, name: "bruno" }
+//   /abc/bruno/index.html     -> 400 "id must be a number ..."
+//   /50/<script>/index.html   -> 400 "name may only contain ..."
+
+use actix_web::{FromRequest, HttpRequest};
+use errors::Error;
+
+
+pub const MIN_ID: u32 = 1;
+pub const MAX_ID: u32 = 1_000_000;
+pub const MAX_NAME_LEN: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub struct UserPath {
+    pub id: u32,
+    pub name: String,
+}
+
+fn parse_id(id: &str) -> Result<u32, String> {
+    let id: u32 = match id.parse() {
+        Err(_) => return Err(format!("id must be a number between {} and {}, got {:?}", MIN_ID, MAX_ID, id)),
+        Ok(id) => id,
+    };
+    if id < MIN_ID || id > MAX_ID {
+        return Err(format!("id must be between {} and {}, got {}", MIN_ID, MAX_ID, id));
+    }
+    Ok(id)
+}
+
+pub fn parse_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("name must have from 1 to {} characters", MAX_NAME_LEN));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!(
+            "name may only contain letters, digits, '-' and '_', got {:?}",
+            name
+        ));
+    }
+    Ok(name.to_string())
+}
+
+impl UserPath {
+    pub fn parse(id: &str, name: &str) -> Result<UserPath, String> {
+        Ok(UserPath { id: parse_id(id)?, name: parse_name(name)? })
+    }
+}
+
+impl<S> FromRequest<S> for UserPath {
+    type Config = ();
+    type Result = Result<UserPath, Error>;
+
+    fn from_request(req: &HttpRequest<S>, _: &Self::Config) -> Self::Result {
+        let params = req.match_info();
+        let id = params.get("id").unwrap_or("");
+        let name = params.get("name").unwrap_or("");
+        UserPath::parse(id, name).map_err(|message| Error::bad_request(req, message))
+    }
+}
+
+
+#[test]
+fn test_parse() {
+  assert_eq!(UserPath::parse("50", "bruno"), Ok(UserPath { id: 50, name: String::from("bruno") }));
+  assert_eq!(UserPath::parse("1000000", "a-b_C9").map(|user| user.id), Ok(MAX_ID));
+}
+
+#[test]
+fn test_invalid_id() {
+  for id in &["", "abc", "-1", "0", "1000001", "4294967296", "1.5"] {
+      assert!(UserPath::parse(id, "bruno").is_err(), "id {:?}", id);
+  }
+}
+
+#[test]
+fn test_invalid_name() {
+  let too_long = "a".repeat(MAX_NAME_LEN + 1);
+  for name in &["", too_long.as_str(), "bruno rocha", "<script>", "bruno%20", "brunó"] {
+      assert!(UserPath::parse("50", name).is_err(), "name {:?}", name);
+  }
+  assert!(UserPath::parse("50", &"a".repeat(MAX_NAME_LEN)).is_ok());
+}
+
+#[test]
+fn test_from_request() {
+  use actix_web::http::StatusCode;
+  use actix_web::test::TestRequest;
+  use errors::Format;
+
+  let req = TestRequest::default().param("id", "50").param("name", "bruno").finish();
+  assert_eq!(UserPath::from_request(&req, &()).unwrap().name, "bruno");
+
+  let req = TestRequest::with_header("Accept", "application/json")
+      .param("id", "abc")
+      .param("name", "bruno")
+      .finish();
+  let err = UserPath::from_request(&req, &()).unwrap_err();
+  assert_eq!(err.status, StatusCode::BAD_REQUEST);
+  assert_eq!(err.format, Format::Json);
+}
@@ -0,0 +1,157 @@
+// `/users` REST resource, JSON in and out
+//
+//   GET    /users?page=1&per_page=20   list, ordered by id
+//   POST   /users        {"name": "bruno"}   -> 201 + Location
+//   GET    /users/{id}
+//   PUT    /users/{id}   {"name": "rocha"}
+//   DELETE /users/{id}                        -> 204
+//
+// The users are kept by the `UserStore` chosen in the config, see store/
+//
+// Every error is the JSON of errors.rs, also the ones found before the
+// handler runs: the body goes through `json_error`, and the id is taken
+// as text and parsed here, `Path<u32>` has no error handler to set.
+//
+// A user is keyed by `id` alone, not by the `{id}/{name}` pair of the
+// greeting route. `name` is data that PUT can change, and a key that
+// changes on a rename would move the user to another url. The greeting
+// route does not look users up, any valid pair is greeted, so the two
+// urls share the id and name rules (user_path.rs) but not the key.
+
+use actix_web::error::JsonPayloadError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{self, HttpRequest, HttpResponse, Json, Path, Query, State};
+use errors::Error;
+use files::Files;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use store::{StoreError, User, UserStore};
+use user_path::parse_name;
+
+
+pub const DEFAULT_PER_PAGE: usize = 20;
+pub const MAX_PER_PAGE: usize = 100;
+
+// The body of POST and PUT, the id comes from the store or the url
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserForm {
+    pub name: String,
+}
+
+// Every worker gets its own App, the store is the same for all of them
+#[derive(Clone)]
+pub struct AppState {
+    pub store: Arc<UserStore>,
+    // None when no static directory is configured
+    pub files: Option<Arc<Files>>,
+    // false once the server is shutting down, see shutdown.rs
+    pub ready: Arc<AtomicBool>,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Pagination {
+    page: usize,
+    per_page: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Pagination {
+        Pagination { page: 1, per_page: DEFAULT_PER_PAGE }
+    }
+}
+
+#[derive(Serialize)]
+struct UserList {
+    users: Vec<User>,
+    page: usize,
+    per_page: usize,
+    total: usize,
+}
+
+fn bad_request(message: String) -> Error {
+    Error::json(StatusCode::BAD_REQUEST, message)
+}
+
+fn not_found(id: u32) -> Error {
+    Error::json(StatusCode::NOT_FOUND, format!("no user with id {}", id))
+}
+
+// the details go to the log, not to the client
+fn internal(e: StoreError) -> Error {
+    eprintln!("{}", e);
+    Error::json(StatusCode::INTERNAL_SERVER_ERROR, String::from("could not reach the user storage"))
+}
+
+fn valid_name(form: UserForm) -> Result<String, Error> {
+    parse_name(&form.name).map_err(bad_request)
+}
+
+fn user_id(id: &str) -> Result<u32, Error> {
+    id.parse().map_err(|_| bad_request(format!("id must be a number, got {:?}", id)))
+}
+
+// The error handler of the `Json<UserForm>` extractor, see `app()`
+pub fn json_error(err: JsonPayloadError, _: HttpRequest<AppState>) -> actix_web::Error {
+    let error = match err {
+        JsonPayloadError::Overflow => Error::json(StatusCode::PAYLOAD_TOO_LARGE, String::from("the body is too large")),
+        JsonPayloadError::ContentType => bad_request(String::from("the body must be sent as application/json")),
+        JsonPayloadError::Deserialize(e) => bad_request(format!("the body is not a user: {}", e)),
+        err => bad_request(format!("could not read the body: {}", err)),
+    };
+    error.into()
+}
+
+pub fn list((state, query): (State<AppState>, Query<Pagination>)) -> Result<HttpResponse, Error> {
+    let Pagination { page, per_page } = query.into_inner();
+    if page == 0 {
+        return Err(bad_request(String::from("page starts at 1")));
+    }
+    if per_page == 0 || per_page > MAX_PER_PAGE {
+        return Err(bad_request(format!("per_page must be between 1 and {}", MAX_PER_PAGE)));
+    }
+
+    let offset = (page - 1).saturating_mul(per_page);
+    let (users, total) = state.store.list(offset, per_page).map_err(internal)?;
+    Ok(HttpResponse::Ok().json(UserList { users, page, per_page, total }))
+}
+
+pub fn create((state, form): (State<AppState>, Json<UserForm>)) -> Result<HttpResponse, Error> {
+    let name = valid_name(form.into_inner())?;
+    let user = match state.store.create(name).map_err(internal)? {
+        None => return Err(Error::json(StatusCode::INSUFFICIENT_STORAGE, String::from("no ids left"))),
+        Some(user) => user,
+    };
+    Ok(HttpResponse::Created()
+        .header(header::LOCATION, format!("/users/{}", user.id))
+        .json(user))
+}
+
+pub fn read((state, id): (State<AppState>, Path<String>)) -> Result<HttpResponse, Error> {
+    let id = user_id(&id)?;
+    match state.store.get(id).map_err(internal)? {
+        None => Err(not_found(id)),
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+    }
+}
+
+pub fn update((state, id, form): (State<AppState>, Path<String>, Json<UserForm>)) -> Result<HttpResponse, Error> {
+    let id = user_id(&id)?;
+    let name = valid_name(form.into_inner())?;
+    match state.store.update(id, name).map_err(internal)? {
+        None => Err(not_found(id)),
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+    }
+}
+
+pub fn delete((state, id): (State<AppState>, Path<String>)) -> Result<HttpResponse, Error> {
+    let id = user_id(&id)?;
+    if state.store.delete(id).map_err(internal)? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(not_found(id))
+    }
+}
+
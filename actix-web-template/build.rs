@@ -1,5 +1,11 @@
 extern crate askama;
 
+use std::env;
+
 fn main() {
-    askama::rerun_if_templates_changed();
+    // with --features dev the running server reads the templates itself,
+    // an edit needs no rebuild (see src/reload.rs)
+    if env::var_os("CARGO_FEATURE_DEV").is_none() {
+        askama::rerun_if_templates_changed();
+    }
 }
@@ -0,0 +1,43 @@
+# English, the complete catalog
+
+site-title = Actix web
+site-header = Actix web + Askama
+site-footer = Made with Rust, actix-web and askama
+nav-home = Home
+nav-about = About
+nav-links = Links
+
+home-title = Home
+home-welcome = Welcome!
+home-question = What is your name?
+form-name = Name
+form-message = Message
+form-send = Send
+form-required = This field is required
+form-too-long = At most { $max } characters
+form-characters-left = { $count } characters left
+flash-message-sent = Thanks, your message was sent.
+
+user-title = Hi, { $name }
+user-greeting = Hi, { $name }!
+user-welcome = Welcome!
+
+login-title = Log in
+login-password = Password
+login-submit = Log in
+login-failed = Wrong name or password
+logged-in-as = Logged in as
+logout = Log out
+flash-logged-in = You are logged in.
+flash-logged-out = You are logged out.
+
+about-title = About
+about-text = An example of server side templates with actix-web and askama. The templates are checked and compiled with the rest of the code.
+
+links-title = Links
+
+error-back = Back to the start page
+error-not-found = There is no page here.
+error-method-not-allowed = This page can not do that.
+error-internal = Something went wrong on our side.
+error-csrf = The form has expired, load the page and send it again.
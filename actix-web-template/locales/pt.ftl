@@ -0,0 +1,43 @@
+# Português
+
+site-title = Actix web
+site-header = Actix web + Askama
+site-footer = Feito com Rust, actix-web e askama
+nav-home = Início
+nav-about = Sobre
+nav-links = Links
+
+home-title = Início
+home-welcome = Bem-vindo!
+home-question = Qual é o seu nome?
+form-name = Nome
+form-message = Mensagem
+form-send = Enviar
+form-required = Este campo é obrigatório
+form-too-long = No máximo { $max } caracteres
+form-characters-left = { $count } caracteres restantes
+flash-message-sent = Obrigado, sua mensagem foi enviada.
+
+user-title = Olá, { $name }
+user-greeting = Olá, { $name }!
+user-welcome = Bem-vindo!
+
+login-title = Entrar
+login-password = Senha
+login-submit = Entrar
+login-failed = Nome ou senha incorretos
+logged-in-as = Conectado como
+logout = Sair
+flash-logged-in = Você entrou.
+flash-logged-out = Você saiu.
+
+about-title = Sobre
+about-text = Um exemplo de templates no servidor com actix-web e askama. Os templates são verificados e compilados junto com o resto do código.
+
+links-title = Links
+
+error-back = Voltar para a página inicial
+error-not-found = Não há nenhuma página aqui.
+error-method-not-allowed = Esta página não faz isso.
+error-internal = Algo deu errado do nosso lado.
+error-csrf = O formulário expirou, carregue a página e envie de novo.
@@ -0,0 +1,97 @@
+// Log in and out, a demo of sessions
+//
+// The session is a cookie signed with SESSION_KEY (32 bytes or more),
+// the browser can read it but not change it. Without SESSION_KEY a
+// random key is made at startup and the sessions end with the server.
+//
+// The accounts are kept in memory with bcrypt hashes of the passwords,
+// one demo account is made at startup from DEMO_USER and DEMO_PASSWORD
+// (demo / demo when they are not set).
+
+use bcrypt;
+use rand::{self, Rng};
+use std::collections::HashMap;
+use std::env;
+
+
+pub const MIN_KEY_LEN: usize = 32;
+
+pub struct Users {
+    hashes: HashMap<String, String>,
+    cost: u32,
+    // checked for unknown names, so they take as long as wrong passwords
+    dummy: String,
+}
+
+impl Users {
+    // `cost` is the bcrypt work factor, bcrypt::DEFAULT_COST outside tests
+    pub fn new(cost: u32) -> Result<Users, String> {
+        let dummy = bcrypt::hash("", cost).map_err(|e| e.to_string())?;
+        Ok(Users { hashes: HashMap::new(), cost, dummy })
+    }
+
+    pub fn add(&mut self, name: &str, password: &str) -> Result<(), String> {
+        let hash = bcrypt::hash(password, self.cost).map_err(|e| e.to_string())?;
+        self.hashes.insert(name.to_string(), hash);
+        Ok(())
+    }
+
+    pub fn verify(&self, name: &str, password: &str) -> bool {
+        match self.hashes.get(name) {
+            Some(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+            None => {
+                let _ = bcrypt::verify(password, &self.dummy);
+                false
+            }
+        }
+    }
+}
+
+// The demo account, with its name
+pub fn demo_users() -> Result<(Users, String), String> {
+    let name = env::var("DEMO_USER").unwrap_or_else(|_| String::from("demo"));
+    let password = env::var("DEMO_PASSWORD").unwrap_or_else(|_| String::from("demo"));
+    let mut users = Users::new(bcrypt::DEFAULT_COST)?;
+    users.add(&name, &password)?;
+    Ok((users, name))
+}
+
+pub fn session_key() -> Result<Vec<u8>, String> {
+    match env::var("SESSION_KEY") {
+        Ok(key) => check_key(key.into_bytes()),
+        Err(_) => {
+            eprintln!("SESSION_KEY is not set, the sessions end when the server stops");
+            let mut key = vec![0u8; MIN_KEY_LEN];
+            rand::thread_rng().fill_bytes(&mut key);
+            Ok(key)
+        }
+    }
+}
+
+fn check_key(key: Vec<u8>) -> Result<Vec<u8>, String> {
+    if key.len() < MIN_KEY_LEN {
+        Err(format!("SESSION_KEY must have at least {} bytes, it has {}", MIN_KEY_LEN, key.len()))
+    } else {
+        Ok(key)
+    }
+}
+
+
+#[test]
+fn test_verify() {
+  // the lowest cost, the tests do not need slow hashes
+  let mut users = Users::new(4).unwrap();
+  users.add("bruno", "s3cret").unwrap();
+  assert!(users.verify("bruno", "s3cret"));
+  assert!(!users.verify("bruno", "S3cret"));
+  assert!(!users.verify("ana", "s3cret"));
+  assert!(!users.verify("ana", ""));
+  // only the hash is kept
+  assert!(!users.hashes["bruno"].contains("s3cret"));
+}
+
+#[test]
+fn test_session_key() {
+  assert!(check_key(b"short".to_vec()).is_err());
+  assert_eq!(check_key(vec![7; 32]), Ok(vec![7; 32]));
+}
@@ -0,0 +1,295 @@
+// Translations of the pages
+//
+// One catalog per language in locales/, read once at startup.
+// The files use a small part of the Fluent syntax (https://projectfluent.org),
+// one message per line and `{ $name }` for a value given by the page:
+//
+//   # comment
+//   user-greeting = Hi, { $name }!
+//
+// The templates see a catalog as `Messages`, one field per message they
+// use: `home-title` is {{ t.home_title }}. Askama and tera (reload.rs)
+// both read struct fields, not keys with a dash, so the fields are
+// listed with their keys in `messages!` below.
+//
+// English is complete, a message missing in another catalog is
+// taken from it. The language of a request is, in this order:
+//   ?lang=pt      and it is remembered in the `lang` cookie
+//   lang cookie
+//   Accept-Language
+//   English
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use time::Duration;
+
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::Cookie;
+use actix_web::middleware::{Middleware, Response};
+use actix_web::{HttpRequest, HttpResponse, Result};
+
+
+pub const DEFAULT_LANG: &str = "en";
+pub const LANG_COOKIE: &str = "lang";
+
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub lang: String,
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(lang: &str, source: &str) -> Result<Catalog, String> {
+        let mut messages = HashMap::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if !key.trim().is_empty() => {
+                    messages.insert(key.trim().to_string(), value.trim().to_string());
+                }
+                _ => return Err(format!("{}.ftl line {}: expected `key = value`", lang, number + 1)),
+            }
+        }
+        Ok(Catalog { lang: lang.to_string(), messages })
+    }
+
+    // The key itself when there is no such message, so a typo shows on the page
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map(|message| message.as_str()).unwrap_or(key)
+    }
+
+    // A message with one `{ $arg }` in it
+    pub fn with(&self, key: &str, arg: &str, value: &str) -> String {
+        self.get(key).replace(&format!("{{ ${} }}", arg), value)
+    }
+}
+
+macro_rules! messages {
+    ($($field:ident: $key:expr),*) => {
+        #[derive(Debug, Serialize)]
+        pub struct Messages<'a> {
+            pub lang: &'a str,
+            $(pub $field: &'a str,)*
+        }
+
+        impl Catalog {
+            pub fn messages(&self) -> Messages {
+                Messages {
+                    lang: &self.lang,
+                    $($field: self.get($key),)*
+                }
+            }
+        }
+
+        #[cfg(test)]
+        const MESSAGE_KEYS: &[&str] = &[$($key),*];
+    }
+}
+
+// The messages the templates use, en.ftl must have them all
+messages! {
+    site_title: "site-title",
+    site_header: "site-header",
+    site_footer: "site-footer",
+    nav_home: "nav-home",
+    nav_about: "nav-about",
+    nav_links: "nav-links",
+    home_title: "home-title",
+    home_welcome: "home-welcome",
+    home_question: "home-question",
+    form_name: "form-name",
+    form_message: "form-message",
+    form_send: "form-send",
+    form_characters_left: "form-characters-left",
+    user_welcome: "user-welcome",
+    about_title: "about-title",
+    about_text: "about-text",
+    links_title: "links-title",
+    error_back: "error-back",
+    login_title: "login-title",
+    login_password: "login-password",
+    login_submit: "login-submit",
+    logged_in_as: "logged-in-as",
+    logout: "logout"
+}
+
+pub struct I18n {
+    catalogs: HashMap<String, Catalog>,
+}
+
+impl I18n {
+    // Every `<lang>.ftl` in `dir`, en.ftl is required
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<I18n, String> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| format!("Can not read {}: {}", dir.display(), e))?;
+        let mut catalogs = HashMap::new();
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("ftl") {
+                continue;
+            }
+            let lang = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(lang) => lang.to_lowercase(),
+                None => continue,
+            };
+            let source = fs::read_to_string(&path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+            catalogs.insert(lang.clone(), Catalog::parse(&lang, &source)?);
+        }
+        I18n::new(catalogs)
+    }
+
+    fn new(mut catalogs: HashMap<String, Catalog>) -> Result<I18n, String> {
+        let default = catalogs
+            .get(DEFAULT_LANG)
+            .cloned()
+            .ok_or_else(|| format!("There is no catalog for {}", DEFAULT_LANG))?;
+        for catalog in catalogs.values_mut() {
+            for (key, message) in &default.messages {
+                catalog.messages.entry(key.clone()).or_insert_with(|| message.clone());
+            }
+        }
+        Ok(I18n { catalogs })
+    }
+
+    pub fn has(&self, lang: &str) -> bool {
+        self.catalogs.contains_key(lang)
+    }
+
+    pub fn catalog(&self, lang: &str) -> &Catalog {
+        self.catalogs.get(lang).unwrap_or_else(|| &self.catalogs[DEFAULT_LANG])
+    }
+
+    // The best language in an Accept-Language header that has a catalog,
+    // `pt-BR` is served in `pt`
+    fn from_accept_language(&self, header: &str) -> Option<&str> {
+        let mut ranges: Vec<(String, f32)> = header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(|part| part.trim());
+                let lang = parts.next()?.split('-').next()?.to_lowercase();
+                let q = parts
+                    .filter_map(|param| {
+                        if param.starts_with("q=") {
+                            param[2..].parse().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .next()
+                    .unwrap_or(1.0);
+                Some((lang, q))
+            })
+            .filter(|&(_, q)| q > 0.0)
+            .collect();
+        // stable, so the header order decides between equal q
+        ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        ranges
+            .into_iter()
+            .find(|&(ref lang, _)| self.has(lang))
+            .map(|(lang, _)| self.catalogs[&lang].lang.as_str())
+    }
+
+    pub fn negotiate(&self, query: Option<&str>, cookie: Option<&str>, accept_language: Option<&str>) -> &Catalog {
+        let chosen = query
+            .into_iter()
+            .chain(cookie)
+            .map(|lang| lang.to_lowercase())
+            .find(|lang| self.has(lang));
+        match chosen {
+            Some(lang) => self.catalog(&lang),
+            None => self.catalog(accept_language.and_then(|header| self.from_accept_language(header)).unwrap_or(DEFAULT_LANG)),
+        }
+    }
+
+    // The catalog for a request
+    pub fn for_request<S>(&self, req: &HttpRequest<S>) -> &Catalog {
+        let query = req.query().get("lang").cloned();
+        let cookie = req.cookie(LANG_COOKIE).map(|cookie| cookie.value().to_string());
+        let accept_language = req.headers().get(header::ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok());
+        self.negotiate(query.as_ref().map(|lang| lang.as_str()), cookie.as_ref().map(|lang| lang.as_str()), accept_language)
+    }
+}
+
+
+// Remembers a `?lang=` choice in the lang cookie
+pub struct RememberLang {
+    i18n: Arc<I18n>,
+}
+
+impl RememberLang {
+    pub fn new(i18n: Arc<I18n>) -> RememberLang {
+        RememberLang { i18n }
+    }
+}
+
+impl<S> Middleware<S> for RememberLang {
+    fn response(&self, req: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        let lang = req.query().get("lang").map(|lang| lang.to_lowercase());
+        if let Some(lang) = lang {
+            // the cookie only ever holds a language there is a catalog for
+            if self.i18n.has(&lang) {
+                let cookie = Cookie::build(LANG_COOKIE, lang)
+                    .path("/")
+                    .max_age(Duration::days(365))
+                    .finish();
+                if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                    resp.headers_mut().append(header::SET_COOKIE, value);
+                }
+            }
+        }
+        Ok(Response::Done(resp))
+    }
+}
+
+
+#[cfg(test)]
+pub fn load_test_catalogs() -> I18n {
+    I18n::load(concat!(env!("CARGO_MANIFEST_DIR"), "/locales")).unwrap()
+}
+
+#[test]
+fn test_parse() {
+  let catalog = Catalog::parse("en", "# greetings\n\nuser-greeting = Hi, { $name }!\nwelcome = Welcome!\n").unwrap();
+  assert_eq!(catalog.get("welcome"), "Welcome!");
+  assert_eq!(catalog.with("user-greeting", "name", "bruno"), "Hi, bruno!");
+  assert_eq!(catalog.get("no-such-key"), "no-such-key");
+  assert!(Catalog::parse("en", "welcome Welcome!").is_err());
+}
+
+#[test]
+fn test_negotiate() {
+  let i18n = load_test_catalogs();
+  assert_eq!(i18n.negotiate(None, None, None).lang, "en");
+  assert_eq!(i18n.negotiate(None, None, Some("pt-BR,pt;q=0.9,en;q=0.8")).lang, "pt");
+  assert_eq!(i18n.negotiate(None, None, Some("de, en;q=0.5, pt;q=0.7")).lang, "pt");
+  assert_eq!(i18n.negotiate(None, None, Some("de")).lang, "en");
+  assert_eq!(i18n.negotiate(None, Some("pt"), Some("en")).lang, "pt");
+  assert_eq!(i18n.negotiate(Some("EN"), Some("pt"), Some("pt")).lang, "en");
+  // an unknown choice falls through to the next source
+  assert_eq!(i18n.negotiate(Some("xx"), None, Some("pt")).lang, "pt");
+}
+
+#[test]
+fn test_catalogs_are_complete() {
+  let i18n = load_test_catalogs();
+  let source = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/locales/pt.ftl")).unwrap();
+  let pt = Catalog::parse("pt", &source).unwrap();
+  for key in i18n.catalog("en").messages.keys() {
+      assert!(pt.messages.contains_key(key), "pt.ftl has no {}", key);
+  }
+}
+
+#[test]
+fn test_templates_have_their_messages() {
+  let i18n = load_test_catalogs();
+  for key in MESSAGE_KEYS {
+      assert!(i18n.catalog("en").messages.contains_key(*key), "en.ftl has no {}", key);
+  }
+  assert_eq!(i18n.catalog("pt").messages().home_welcome, "Bem-vindo!");
+}
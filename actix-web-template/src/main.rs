@@ -2,51 +2,905 @@ extern crate actix;
 extern crate actix_web;
 #[macro_use]
 extern crate askama;
+extern crate bcrypt;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate time;
+#[cfg(feature = "dev")]
+#[macro_use]
+extern crate lazy_static;
+#[cfg(feature = "dev")]
+extern crate notify;
+#[cfg(feature = "dev")]
+extern crate tera;
+
+mod auth;
+mod i18n;
+mod security;
+#[cfg(test)]
+mod tests;
+#[cfg(feature = "dev")]
+mod reload;
 
-use std::collections::HashMap;
+use std::env;
+use std::process;
+use std::sync::Arc;
 
-use actix_web::{http, server, App, HttpResponse, Query, Result};
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::{header, Cookie, StatusCode};
+use actix_web::middleware::session::{CookieSessionBackend, RequestSession, SessionStorage};
+use actix_web::{http, server, App, Form, HttpRequest, HttpResponse, Result};
+use auth::Users;
 use askama::Template;
+use i18n::{Catalog, I18n, Messages, RememberLang};
+use serde::Serialize;
+
+
+// Longest values the form accepts, in characters
+const MAX_NAME_LEN: usize = 32;
+const MAX_MESSAGE_LEN: usize = 500;
+
+// The cookie that carries a flash message to the next page
+const FLASH_COOKIE: &str = "flash";
+
+// The name of the logged in user, in the session
+const USER_KEY: &str = "user";
+
+
+// What the user typed, sent back when it is not valid
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct MessageForm {
+    name: String,
+    message: String,
+    #[serde(skip_serializing)]
+    csrf_token: String,
+}
+
+// One message per field, empty when the field is fine
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct FormErrors {
+    name: String,
+    message: String,
+}
+
+impl FormErrors {
+    fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.message.is_empty()
+    }
+}
+
+fn check_field(t: &Catalog, value: &str, max_len: usize) -> String {
+    let len = value.trim().chars().count();
+    if len == 0 {
+        t.get("form-required").to_string()
+    } else if len > max_len {
+        t.with("form-too-long", "max", &max_len.to_string())
+    } else {
+        String::new()
+    }
+}
+
+impl MessageForm {
+    // The errors are in the language of the page
+    fn validate(&self, t: &Catalog) -> FormErrors {
+        FormErrors {
+            name: check_field(t, &self.name, MAX_NAME_LEN),
+            message: check_field(t, &self.message, MAX_MESSAGE_LEN),
+        }
+    }
+}
+
+// Shown once, on the page after a redirect.
+// The cookie only holds the code, never text from the user.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flash {
+    MessageSent,
+    LoggedIn,
+    LoggedOut,
+}
+
+impl Flash {
+    fn code(&self) -> &'static str {
+        match *self {
+            Flash::MessageSent => "message-sent",
+            Flash::LoggedIn => "logged-in",
+            Flash::LoggedOut => "logged-out",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Flash> {
+        match code {
+            "message-sent" => Some(Flash::MessageSent),
+            "logged-in" => Some(Flash::LoggedIn),
+            "logged-out" => Some(Flash::LoggedOut),
+            _ => None,
+        }
+    }
+
+    // in the catalogs
+    fn key(&self) -> &'static str {
+        match *self {
+            Flash::MessageSent => "flash-message-sent",
+            Flash::LoggedIn => "flash-logged-in",
+            Flash::LoggedOut => "flash-logged-out",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LoginForm {
+    name: String,
+    password: String,
+    csrf_token: String,
+}
+
+// The logout button, a form with only the token
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CsrfForm {
+    csrf_token: String,
+}
+
 
+// Every worker gets its own App, the catalogs and the users are loaded once
+#[derive(Clone)]
+struct AppState {
+    i18n: Arc<I18n>,
+    users: Arc<Users>,
+}
+
+
+// --- Pages ---
+//
+// Every page extends base.html (header, nav and footer partials) and
+// gets the base through `_parent`. The pages fill the `title`,
+// `content` and, when they need it, `scripts` blocks.
+// `t` has the messages in the language of the request, `{{ t.home_title }}`.
+//
+// A page is rendered by askama, or by tera with `--features dev`
+// (see reload.rs). The serde attributes give tera the same names:
+// the base fields are flattened into the page.
+
+trait Page: Template + Serialize {
+    // in templates/, as in #[template(path)]
+    const FILE: &'static str;
+}
+
+// The links in the nav, in order, with the catalog key of their label
+const NAV: &[(&str, &str)] = &[("/", "nav-home"), ("/about", "nav-about"), ("/links", "nav-links")];
+
+#[derive(Serialize)]
+struct NavLink<'a> {
+    href: &'static str,
+    label: &'a str,
+    active: bool,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "base.html")]
+struct Base<'a> {
+    t: Messages<'a>,
+    nav: Vec<NavLink<'a>>,
+    // empty for no flash message
+    flash: &'a str,
+    // empty when nobody is logged in
+    user: String,
+    // for the logout form in the header
+    csrf_token: String,
+}
 
-#[derive(Template)]
+impl<'a> Base<'a> {
+    // `path` is the path of the request, its link is highlighted
+    fn new(catalog: &'a Catalog, path: &str, flash: Option<Flash>) -> Base<'a> {
+        let nav = NAV
+            .iter()
+            .map(|&(href, key)| NavLink { href, label: catalog.get(key), active: href == path })
+            .collect();
+        Base {
+            t: catalog.messages(),
+            nav: nav,
+            flash: flash.map(|flash| catalog.get(flash.key())).unwrap_or(""),
+            user: String::new(),
+            csrf_token: String::new(),
+        }
+    }
+
+    fn session(mut self, user: Option<String>, csrf_token: &str) -> Base<'a> {
+        self.user = user.unwrap_or_default();
+        self.csrf_token = csrf_token.to_string();
+        self
+    }
+}
+
+#[derive(Template, Serialize)]
 #[template(path = "index.html")]
-struct Index;
+struct Index<'a> {
+    #[serde(skip)]
+    t: &'a Messages<'a>,
+    // "Home", or "Hi, bruno" when logged in, in <title>
+    title: &'a str,
+    // "Hi, bruno!" above the form, empty when logged out
+    greeting: &'a str,
+    form: &'a MessageForm,
+    errors: &'a FormErrors,
+    // the same as in the base, tera gets it from there
+    #[serde(skip)]
+    csrf_token: &'a str,
+    #[serde(flatten)]
+    _parent: &'a Base<'a>,
+}
+
+impl<'a> Page for Index<'a> {
+    const FILE: &'static str = "index.html";
+}
 
-#[derive(Template)]
-#[template(path = "user.html")]
-struct UserTemplate<'a> {
+#[derive(Template, Serialize)]
+#[template(path = "login.html")]
+struct LoginTemplate<'a> {
+    #[serde(skip)]
+    t: &'a Messages<'a>,
+    // what was typed, never the password
     name: &'a str,
-    text: &'a str,
-    _parent: &'a Index,
+    // empty for no error
+    error: &'a str,
+    #[serde(skip)]
+    csrf_token: &'a str,
+    #[serde(flatten)]
+    _parent: &'a Base<'a>,
 }
 
+impl<'a> Page for LoginTemplate<'a> {
+    const FILE: &'static str = "login.html";
+}
 
-fn index(query: Query<HashMap<String, String>>) -> Result<HttpResponse> {
-    let s = if let Some(name) = query.get("name") {
-        UserTemplate {
-            name: name,
-            text: "Welcome!",
-            _parent: &Index
-        }.render()
-            .unwrap()
-    } else {
-        Index.render().unwrap()
+#[derive(Template, Serialize)]
+#[template(path = "about.html")]
+struct About<'a> {
+    #[serde(skip)]
+    t: &'a Messages<'a>,
+    #[serde(flatten)]
+    _parent: &'a Base<'a>,
+}
+
+impl<'a> Page for About<'a> {
+    const FILE: &'static str = "about.html";
+}
+
+#[derive(Serialize)]
+struct Link {
+    label: &'static str,
+    href: &'static str,
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "links.html")]
+struct Links<'a> {
+    #[serde(skip)]
+    t: &'a Messages<'a>,
+    links: &'a [Link],
+    #[serde(flatten)]
+    _parent: &'a Base<'a>,
+}
+
+impl<'a> Page for Links<'a> {
+    const FILE: &'static str = "links.html";
+}
+
+#[derive(Template, Serialize)]
+#[template(path = "error.html")]
+struct ErrorTemplate<'a> {
+    #[serde(skip)]
+    t: &'a Messages<'a>,
+    status: u16,
+    reason: &'a str,
+    message: &'a str,
+    #[serde(flatten)]
+    _parent: &'a Base<'a>,
+}
+
+impl<'a> Page for ErrorTemplate<'a> {
+    const FILE: &'static str = "error.html";
+}
+
+
+#[cfg(not(feature = "dev"))]
+fn render_page<T: Page>(page: &T) -> std::result::Result<String, String> {
+    page.render().map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "dev")]
+fn render_page<T: Page>(page: &T) -> std::result::Result<String, String> {
+    reload::render(T::FILE, page)
+}
+
+fn html(status: StatusCode, body: String) -> HttpResponse {
+    HttpResponse::build(status).content_type("text/html; charset=utf-8").body(body)
+}
+
+// `message` is a catalog key
+fn error_page(catalog: &Catalog, path: &str, status: StatusCode, message: &str) -> HttpResponse {
+    let reason = status.canonical_reason().unwrap_or("Error");
+    let base = Base::new(catalog, path, None);
+    let page = render_page(&ErrorTemplate {
+        t: &base.t,
+        status: status.as_u16(),
+        reason: reason,
+        message: catalog.get(message),
+        _parent: &base,
+    });
+    match page {
+        Ok(body) => html(status, body),
+        // the error page itself is broken, plain text still works
+        Err(_) => HttpResponse::build(status)
+            .content_type("text/plain; charset=utf-8")
+            .body(format!("{} {}", status.as_u16(), reason)),
+    }
+}
+
+// A template that fails is a 500 page, the worker goes on
+fn render<T: Page>(catalog: &Catalog, status: StatusCode, page: &T) -> HttpResponse {
+    render_with(catalog, HttpResponse::build(status), page)
+}
+
+// The same, for a response that already has its status, headers or cookies
+fn render_with<T: Page>(catalog: &Catalog, mut response: HttpResponseBuilder, page: &T) -> HttpResponse {
+    match render_page(page) {
+        Ok(body) => response.content_type("text/html; charset=utf-8").body(body),
+        Err(e) => {
+            eprintln!("Can not render the page: {}", e);
+            error_page(catalog, "", StatusCode::INTERNAL_SERVER_ERROR, "error-internal")
+        }
+    }
+}
+
+fn flash_cookie(value: &str) -> Cookie<'static> {
+    Cookie::build(FLASH_COOKIE, value.to_string()).path("/").http_only(true).finish()
+}
+
+// See other (303), so a reload does not post the form again
+fn redirect(location: &str, flash: Option<Flash>) -> HttpResponse {
+    let mut response = HttpResponse::SeeOther();
+    response.header(header::LOCATION, location);
+    if let Some(flash) = flash {
+        response.cookie(flash_cookie(flash.code()));
+    }
+    response.finish()
+}
+
+// The title and greeting of the home page, with the name when logged in
+fn home_titles(catalog: &Catalog, user: Option<&str>) -> (String, String) {
+    match user {
+        Some(user) => (catalog.with("user-title", "name", user), catalog.with("user-greeting", "name", user)),
+        None => (catalog.get("home-title").to_string(), String::new()),
+    }
+}
+
+// The name in the session, None when logged out
+fn session_user<S>(req: &mut HttpRequest<S>) -> Option<String> {
+    req.session().get::<String>(USER_KEY).unwrap_or(None)
+}
+
+fn index(mut req: HttpRequest<AppState>) -> HttpResponse {
+    let user = session_user(&mut req);
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let flash = req.cookie(FLASH_COOKIE).and_then(|cookie| Flash::from_code(cookie.value()));
+    let (title, greeting) = home_titles(catalog, user.as_ref().map(|user| user.as_str()));
+    let base = Base::new(catalog, req.path(), flash).session(user, &csrf_token);
+    let mut response = HttpResponse::Ok();
+    if flash.is_some() {
+        // shown once
+        response.del_cookie(&flash_cookie(""));
+    }
+    render_with(catalog, response, &Index {
+        t: &base.t,
+        title: &title,
+        greeting: &greeting,
+        form: &MessageForm::default(),
+        errors: &FormErrors::default(),
+        csrf_token: &csrf_token,
+        _parent: &base
+    })
+}
+
+// A form sent from another site, or from a page of an older session
+fn csrf_failed(req: &HttpRequest<AppState>) -> HttpResponse {
+    let catalog = req.state().i18n.for_request(req);
+    error_page(catalog, req.path(), StatusCode::FORBIDDEN, "error-csrf")
+}
+
+// POST /
+fn send((form, mut req): (Form<MessageForm>, HttpRequest<AppState>)) -> HttpResponse {
+    if !security::csrf_ok(&mut req, &form.csrf_token) {
+        return csrf_failed(&req);
+    }
+    let user = session_user(&mut req);
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let form = form.into_inner();
+    let errors = form.validate(catalog);
+    if !errors.is_empty() {
+        let (title, greeting) = home_titles(catalog, user.as_ref().map(|user| user.as_str()));
+        let base = Base::new(catalog, req.path(), None).session(user, &csrf_token);
+        let page = Index {
+            t: &base.t,
+            title: &title,
+            greeting: &greeting,
+            form: &form,
+            errors: &errors,
+            csrf_token: &csrf_token,
+            _parent: &base,
+        };
+        return render(catalog, StatusCode::UNPROCESSABLE_ENTITY, &page);
+    }
+
+    // the message goes nowhere, this is an example of templates and forms
+    redirect("/", Some(Flash::MessageSent))
+}
+
+fn login_page(mut req: HttpRequest<AppState>) -> HttpResponse {
+    if session_user(&mut req).is_some() {
+        return redirect("/", None);
+    }
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let base = Base::new(catalog, req.path(), None).session(None, &csrf_token);
+    let page = LoginTemplate { t: &base.t, name: "", error: "", csrf_token: &csrf_token, _parent: &base };
+    render(catalog, StatusCode::OK, &page)
+}
+
+// POST /login
+fn login((form, mut req): (Form<LoginForm>, HttpRequest<AppState>)) -> Result<HttpResponse> {
+    if !security::csrf_ok(&mut req, &form.csrf_token) {
+        return Ok(csrf_failed(&req));
+    }
+    let name = form.name.trim();
+    if req.state().users.verify(name, &form.password) {
+        // a token seen before the login is no good after it
+        security::renew_csrf_token(&mut req);
+        req.session().set(USER_KEY, name)?;
+        return Ok(redirect("/", Some(Flash::LoggedIn)));
+    }
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let base = Base::new(catalog, req.path(), None).session(None, &csrf_token);
+    // the same message for an unknown name and a wrong password
+    let page = LoginTemplate {
+        t: &base.t,
+        name: name,
+        error: catalog.get("login-failed"),
+        csrf_token: &csrf_token,
+        _parent: &base,
     };
-    Ok(HttpResponse::Ok().content_type("text/html").body(s))
+    Ok(render(catalog, StatusCode::FORBIDDEN, &page))
+}
+
+// POST /logout, a link could log out by being loaded in an <img>
+fn logout((form, mut req): (Form<CsrfForm>, HttpRequest<AppState>)) -> HttpResponse {
+    if !security::csrf_ok(&mut req, &form.csrf_token) {
+        return csrf_failed(&req);
+    }
+    // the token goes with the rest of the session
+    req.session().clear();
+    redirect("/", Some(Flash::LoggedOut))
+}
+
+fn about(mut req: HttpRequest<AppState>) -> HttpResponse {
+    let user = session_user(&mut req);
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let base = Base::new(catalog, req.path(), None).session(user, &csrf_token);
+    render(catalog, StatusCode::OK, &About { t: &base.t, _parent: &base })
+}
+
+fn links(mut req: HttpRequest<AppState>) -> HttpResponse {
+    let user = session_user(&mut req);
+    let csrf_token = security::csrf_token(&mut req);
+    let catalog = req.state().i18n.for_request(&req);
+    let links = [
+        Link { label: "Actix web", href: "https://actix.rs/" },
+        Link { label: "Askama", href: "https://github.com/djc/askama" },
+        Link { label: "The Rust book", href: "https://doc.rust-lang.org/book/" },
+    ];
+    let base = Base::new(catalog, req.path(), None).session(user, &csrf_token);
+    render(catalog, StatusCode::OK, &Links { t: &base.t, links: &links, _parent: &base })
+}
+
+// The files in static/, with their content type. The pages have no
+// inline script or style, the Content-Security-Policy forbids them.
+const STATIC_FILES: &[(&str, &str, &str)] = &[
+    ("form.js", "application/javascript; charset=utf-8", include_str!("../static/form.js")),
+    ("style.css", "text/css; charset=utf-8", include_str!("../static/style.css")),
+];
+
+// GET /static/{file}
+fn static_file(req: HttpRequest<AppState>) -> HttpResponse {
+    let file = STATIC_FILES.iter().find(|&&(name, _, _)| Some(name) == req.match_info().get("file"));
+    match file {
+        Some(&(_, content_type, body)) => HttpResponse::Ok().content_type(content_type).body(body),
+        None => not_found(req),
+    }
+}
+
+fn not_found(req: HttpRequest<AppState>) -> HttpResponse {
+    let catalog = req.state().i18n.for_request(&req);
+    error_page(catalog, req.path(), StatusCode::NOT_FOUND, "error-not-found")
+}
+
+// The routes above it took every method the page knows, they are in `allow`
+fn method_not_allowed(req: HttpRequest<AppState>, allow: &'static str) -> HttpResponse {
+    let catalog = req.state().i18n.for_request(&req);
+    let mut response = error_page(catalog, req.path(), StatusCode::METHOD_NOT_ALLOWED, "error-method-not-allowed");
+    response.headers_mut().insert(header::ALLOW, header::HeaderValue::from_static(allow));
+    response
+}
+
+
+// The routes and middleware, main serves them and the tests too
+fn app(state: AppState, session_key: &[u8]) -> App<AppState> {
+    let i18n = state.i18n.clone();
+    App::with_state(state)
+        .middleware(RememberLang::new(i18n))
+        .middleware(security::SecurityHeaders)
+        .middleware(SessionStorage::new(
+            CookieSessionBackend::signed(session_key)
+                .name("session")
+                // true when served over https
+                .secure(false),
+        ))
+        .resource("/", |r| {
+            r.method(http::Method::GET).f(index);
+            r.method(http::Method::POST).with(send);
+            r.route().f(|req| method_not_allowed(req, "GET, POST"));
+        })
+        .resource("/login", |r| {
+            r.method(http::Method::GET).f(login_page);
+            r.method(http::Method::POST).with(login);
+            r.route().f(|req| method_not_allowed(req, "GET, POST"));
+        })
+        .resource("/logout", |r| {
+            r.method(http::Method::POST).with(logout);
+            r.route().f(|req| method_not_allowed(req, "POST"));
+        })
+        .resource("/about", |r| {
+            r.method(http::Method::GET).f(about);
+            r.route().f(|req| method_not_allowed(req, "GET"));
+        })
+        .resource("/links", |r| {
+            r.method(http::Method::GET).f(links);
+            r.route().f(|req| method_not_allowed(req, "GET"));
+        })
+        .resource("/static/{file}", |r| {
+            r.method(http::Method::GET).f(static_file);
+            r.route().f(|req| method_not_allowed(req, "GET"));
+        })
+        .default_resource(|r| r.f(not_found))
 }
 
 
 fn main() {
+    let locales = env::var("LOCALES_DIR").unwrap_or_else(|_| String::from("locales"));
+    let i18n = match I18n::load(&locales) {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(i18n) => Arc::new(i18n),
+    };
+    let (users, demo) = match auth::demo_users() {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(users) => users,
+    };
+    let session_key = match auth::session_key() {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(key) => key,
+    };
+    let state = AppState { i18n: i18n, users: Arc::new(users) };
+
+    #[cfg(feature = "dev")]
+    reload::watch();
+
     let sys = actix::System::new("template-askama");
 
     // start http server
-    server::new(move || {
-        App::new().resource("/", |r| r.method(http::Method::GET).with(index))
-    }).bind("0.0.0.0:8080")
+    server::new(move || app(state.clone(), &session_key))
+        .bind("0.0.0.0:8080")
         .unwrap()
         .start();
 
     println!("Started http server: 127.0.0.1:8080");
+    println!("Log in as {}, the password is in DEMO_PASSWORD (demo when not set)", demo);
     let _ = sys.run();
 }
+
+
+#[cfg(test)]
+fn test_state() -> AppState {
+    let mut users = Users::new(4).unwrap();
+    users.add("bruno", "s3cret").unwrap();
+    AppState { i18n: Arc::new(i18n::load_test_catalogs()), users: Arc::new(users) }
+}
+
+#[test]
+fn test_validate() {
+  let state = test_state();
+  let en = state.i18n.catalog("en");
+  let form = MessageForm { name: String::from("bruno"), message: String::from("hello"), ..MessageForm::default() };
+  assert!(form.validate(en).is_empty());
+
+  let errors = MessageForm { name: String::from("   "), message: "x".repeat(MAX_MESSAGE_LEN + 1), ..MessageForm::default() }.validate(en);
+  assert_eq!(errors.name, "This field is required");
+  assert_eq!(errors.message, "At most 500 characters");
+
+  // characters, not bytes
+  let form = MessageForm { name: "ã".repeat(MAX_NAME_LEN), message: String::from("olá"), ..MessageForm::default() };
+  assert!(form.validate(en).is_empty());
+
+  let errors = MessageForm::default().validate(state.i18n.catalog("pt"));
+  assert_eq!(errors.name, "Este campo é obrigatório");
+}
+
+#[test]
+fn test_flash_codes() {
+  assert_eq!(Flash::from_code(Flash::MessageSent.code()), Some(Flash::MessageSent));
+  assert_eq!(Flash::from_code("<script>"), None);
+}
+
+#[test]
+fn test_form_is_filled_back_with_errors() {
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  let form = MessageForm { name: String::from("\"><b>bruno"), ..MessageForm::default() };
+  let errors = form.validate(catalog);
+  let base = Base::new(catalog, "/", None);
+  let page = render_page(&Index { t: &base.t, title: "Home", greeting: "", form: &form, errors: &errors, csrf_token: "", _parent: &base }).unwrap();
+  assert!(page.contains("This field is required"));
+  assert!(page.contains("&quot;&gt;&lt;b&gt;bruno"));
+  assert!(!page.contains("<b>bruno"));
+}
+
+#[test]
+fn test_render_error_is_a_500_page() {
+  use std::fmt;
+
+  #[derive(Serialize)]
+  struct Broken;
+
+  impl fmt::Display for Broken {
+      fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+          Err(fmt::Error)
+      }
+  }
+
+  #[derive(Template, Serialize)]
+  #[template(source = "<p>{{ value }}</p>", ext = "html")]
+  struct BrokenTemplate {
+      value: Broken,
+  }
+
+  // and tera has no such file
+  impl Page for BrokenTemplate {
+      const FILE: &'static str = "broken.html";
+  }
+
+  let state = test_state();
+  let response = render(state.i18n.catalog("en"), StatusCode::OK, &BrokenTemplate { value: Broken });
+  assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+  assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+}
+
+#[test]
+fn test_error_pages() {
+  use actix_web::test::TestRequest;
+
+  let response = not_found(TestRequest::with_state(test_state()).finish());
+  assert_eq!(response.status(), StatusCode::NOT_FOUND);
+  let response = method_not_allowed(TestRequest::with_state(test_state()).finish(), "POST");
+  assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+  assert_eq!(response.headers()["allow"], "POST");
+
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  let base = Base::new(catalog, "/nothing", None);
+  let page = render_page(&ErrorTemplate {
+      t: &base.t,
+      status: 404,
+      reason: "Not Found",
+      message: catalog.get("error-not-found"),
+      _parent: &base,
+  }).unwrap();
+  assert!(page.contains("<h1>404 Not Found</h1>"));
+  assert!(page.contains("There is no page here."));
+  // the base page is around it
+  assert!(page.contains("<title>404 Not Found - Actix web</title>"));
+  // no page is active on an error page
+  assert!(!page.contains("class=\"active\""));
+  assert!(page.contains("<footer>"));
+}
+
+#[test]
+fn test_nav_highlights_the_page() {
+  let state = test_state();
+  let base = Base::new(state.i18n.catalog("en"), "/about", None);
+  let page = render_page(&About { t: &base.t, _parent: &base }).unwrap();
+  assert!(page.contains("<title>About - Actix web</title>"));
+  assert!(page.contains(r#"<a href="/about" class="active" aria-current="page">About</a>"#));
+  assert!(page.contains(r#"<a href="/">Home</a>"#));
+}
+
+#[test]
+fn test_links_page() {
+  let state = test_state();
+  let base = Base::new(state.i18n.catalog("en"), "/links", None);
+  let links = [Link { label: "Askama", href: "https://github.com/djc/askama" }];
+  let page = render_page(&Links { t: &base.t, links: &links, _parent: &base }).unwrap();
+  assert!(page.contains(r#"<li><a href="https://github.com/djc/askama">Askama</a></li>"#));
+}
+
+#[test]
+fn test_flash_is_in_the_layout() {
+  let state = test_state();
+  let base = Base::new(state.i18n.catalog("en"), "/", Some(Flash::MessageSent));
+  let page = render_page(&About { t: &base.t, _parent: &base }).unwrap();
+  assert!(page.contains(r#"<p class="flash">Thanks, your message was sent.</p>"#));
+  // the script block is only filled by the form page
+  assert!(!page.contains("form.js"));
+}
+
+#[test]
+fn test_pages_in_portuguese() {
+  use actix_web::test::TestRequest;
+
+  let state = test_state();
+  let catalog = state.i18n.catalog("pt");
+  let base = Base::new(catalog, "/", Some(Flash::MessageSent));
+  let title = catalog.with("user-title", "name", "bruno");
+  let greeting = catalog.with("user-greeting", "name", "bruno");
+  let form = MessageForm::default();
+  let errors = FormErrors::default();
+  let page = render_page(&Index {
+      t: &base.t,
+      title: &title,
+      greeting: &greeting,
+      form: &form,
+      errors: &errors,
+      csrf_token: "",
+      _parent: &base,
+  }).unwrap();
+  assert!(page.contains(r#"<html lang="pt">"#));
+  assert!(page.contains("<title>Olá, bruno - Actix web</title>"));
+  assert!(page.contains("<h1>Olá, bruno!</h1>"));
+  assert!(page.contains("Bem-vindo!"));
+  assert!(page.contains("Obrigado, sua mensagem foi enviada."));
+  assert!(page.contains(r#"<a href="/" class="active" aria-current="page">Início</a>"#));
+
+  let req = TestRequest::with_state(test_state()).header("Accept-Language", "pt-BR,pt;q=0.9").finish();
+  assert_eq!(req.state().i18n.for_request(&req).lang, "pt");
+  let req = TestRequest::with_state(test_state()).uri("/about?lang=en").header("Accept-Language", "pt").finish();
+  assert_eq!(req.state().i18n.for_request(&req).lang, "en");
+}
+
+#[test]
+fn test_logged_in_user_in_the_header() {
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  let base = Base::new(catalog, "/", Some(Flash::LoggedIn)).session(Some(String::from("bruno")), "t0k3n");
+  let greeting = catalog.with("user-greeting", "name", "bruno");
+  let form = MessageForm::default();
+  let errors = FormErrors::default();
+  let page = render_page(&Index {
+      t: &base.t,
+      title: "Hi, bruno",
+      greeting: &greeting,
+      form: &form,
+      errors: &errors,
+      csrf_token: "t0k3n",
+      _parent: &base,
+  }).unwrap();
+  assert!(page.contains("Logged in as <strong>bruno</strong>"));
+  assert!(page.contains(r#"<form method="post" action="/logout""#));
+  assert!(page.contains(r#"<input type="hidden" name="csrf_token" value="t0k3n" />"#));
+  assert!(page.contains("<title>Hi, bruno - Actix web</title>"));
+  assert!(page.contains("<h1>Hi, bruno!</h1>"));
+  // and the form is still there
+  assert!(page.contains(r#"<form method="post" action="/">"#));
+
+  let base = Base::new(catalog, "/", None);
+  let page = render_page(&Index {
+      t: &base.t,
+      title: "Home",
+      greeting: "",
+      form: &MessageForm::default(),
+      errors: &FormErrors::default(),
+      csrf_token: "",
+      _parent: &base,
+  }).unwrap();
+  assert!(page.contains(r#"<a href="/login">Log in</a>"#));
+  assert!(!page.contains("/logout"));
+}
+
+#[test]
+fn test_failed_login_page() {
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  let base = Base::new(catalog, "/login", None);
+  let page = LoginTemplate {
+      t: &base.t,
+      name: "bruno",
+      error: catalog.get("login-failed"),
+      csrf_token: "t0k3n",
+      _parent: &base,
+  };
+  let page = render_page(&page).unwrap();
+  assert!(page.contains("Wrong name or password"));
+  assert!(page.contains(r#"value="bruno""#));
+  assert!(page.contains(r#"<input type="hidden" name="csrf_token" value="t0k3n" />"#));
+  assert!(state.users.verify("bruno", "s3cret"));
+}
+
+// Whatever the user sends comes back escaped, in every place a page shows it
+#[test]
+fn test_user_input_is_escaped() {
+  let payloads = [
+      "<script>alert(1)</script>",
+      "\"><img src=x onerror=alert(1)>",
+      "' onmouseover='alert(1)",
+      "</textarea><script>alert(1)</script>",
+  ];
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  for payload in &payloads {
+      let base = Base::new(catalog, "/", None).session(Some(payload.to_string()), payload);
+
+      // text: the greeting, in <title> and <h1>, and the user in the header,
+      // attributes and <textarea>: the form sent back
+      let title = catalog.with("user-title", "name", payload);
+      let greeting = catalog.with("user-greeting", "name", payload);
+      let form = MessageForm { name: payload.to_string(), message: payload.to_string(), ..MessageForm::default() };
+      let errors = form.validate(catalog);
+      let page = render_page(&Index {
+          t: &base.t,
+          title: &title,
+          greeting: &greeting,
+          form: &form,
+          errors: &errors,
+          csrf_token: payload,
+          _parent: &base,
+      }).unwrap();
+      assert!(!page.contains(payload), "{} in the form page", payload);
+
+      let page = render_page(&LoginTemplate {
+          t: &base.t,
+          name: payload,
+          error: "",
+          csrf_token: payload,
+          _parent: &base,
+      }).unwrap();
+      assert!(!page.contains(payload), "{} in the login page", payload);
+  }
+}
+
+#[test]
+fn test_pages_have_no_inline_code() {
+  let state = test_state();
+  let catalog = state.i18n.catalog("en");
+  let base = Base::new(catalog, "/", None);
+  let form = MessageForm::default();
+  let errors = FormErrors::default();
+  let page = render_page(&Index { t: &base.t, title: "Home", greeting: "", form: &form, errors: &errors, csrf_token: "", _parent: &base }).unwrap();
+  // the Content-Security-Policy would block them
+  assert!(!page.contains("<style>"));
+  assert!(!page.contains("<script>"));
+  assert!(page.contains(r#"<link rel="stylesheet" href="/static/style.css" />"#));
+  assert!(page.contains(r#"<script src="/static/form.js"></script>"#));
+}
@@ -0,0 +1,65 @@
+// Development mode: cargo run --features dev
+//
+// The templates are read by tera when the server starts, and read
+// again when a file in templates/ changes: edit, save, reload the
+// browser, no recompile. The normal build compiles them with askama.
+//
+// Both engines render the same files, so the templates only use what
+// both understand: `{{ a.b }}`, `{% if a %}` and `{% if a != "" %}`,
+// `{% for %}`, `{% extends %}`, `{% block %}`, `{% include %}` and
+// the `safe` filter. The tests run with either engine:
+//
+//   cargo test && cargo test --features dev
+
+use notify::{self, DebouncedEvent, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::sync::mpsc;
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+use tera::Tera;
+
+
+const TEMPLATES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/templates");
+
+lazy_static! {
+    static ref TERA: RwLock<Tera> = RwLock::new(load().unwrap_or_else(|e| panic!("{}", e)));
+}
+
+fn load() -> Result<Tera, String> {
+    Tera::new(&format!("{}/**/*.html", TEMPLATES)).map_err(|e| format!("Can not load the templates: {}", e))
+}
+
+pub fn render<T: Serialize>(file: &str, page: &T) -> Result<String, String> {
+    TERA.read().unwrap().render(file, page).map_err(|e| e.to_string())
+}
+
+fn reload() {
+    match load() {
+        Ok(tera) => {
+            *TERA.write().unwrap() = tera;
+            println!("Templates reloaded");
+        }
+        // the pages keep the last templates that worked
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+// Loads the templates now, a mistake in them stops the server from
+// starting, then watches them in a thread of its own
+pub fn watch() {
+    drop(TERA.read().unwrap());
+    thread::spawn(|| {
+        let (sender, receiver) = mpsc::channel();
+        // an editor saving a file sends a few events, they come as one
+        let mut watcher = notify::watcher(sender, Duration::from_millis(200)).expect("Can not watch the templates");
+        watcher.watch(TEMPLATES, RecursiveMode::Recursive).expect("Can not watch the templates");
+        println!("Watching {} for changes", TEMPLATES);
+        for event in receiver {
+            match event {
+                DebouncedEvent::Create(_) | DebouncedEvent::Write(_) | DebouncedEvent::Remove(_) | DebouncedEvent::Rename(_, _) => reload(),
+                _ => (),
+            }
+        }
+    });
+}
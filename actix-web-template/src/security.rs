@@ -0,0 +1,115 @@
+// Protections for every page
+//
+// SecurityHeaders sets:
+//   Content-Security-Policy    only scripts, styles and images from this
+//                              site, no inline script, the CSS and JS are
+//                              files in static/
+//   X-Frame-Options            no page in a frame of another site
+//   Referrer-Policy            the url is not sent to other sites
+//   Strict-Transport-Security  https only, browsers ignore it over http
+//   X-Content-Type-Options     the Content-Type is what the file is
+//
+// Every form has a hidden `csrf_token`, a random token kept in the
+// session. A POST without the token of its session is refused, so
+// another site can not send forms in the name of the user.
+
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::session::RequestSession;
+use actix_web::middleware::{Middleware, Response};
+use actix_web::{HttpRequest, HttpResponse, Result};
+use rand::{self, Rng};
+
+
+const CSRF_KEY: &str = "csrf_token";
+
+const HEADERS: &[(&str, &str)] = &[
+    (
+        "content-security-policy",
+        "default-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'",
+    ),
+    ("x-frame-options", "DENY"),
+    ("referrer-policy", "same-origin"),
+    ("strict-transport-security", "max-age=31536000; includeSubDomains"),
+    ("x-content-type-options", "nosniff"),
+];
+
+pub struct SecurityHeaders;
+
+impl<S> Middleware<S> for SecurityHeaders {
+    fn response(&self, _: &mut HttpRequest<S>, mut resp: HttpResponse) -> Result<Response> {
+        for &(name, value) in HEADERS {
+            resp.headers_mut()
+                .insert(HeaderName::from_static(name), HeaderValue::from_static(value));
+        }
+        Ok(Response::Done(resp))
+    }
+}
+
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// The token of the session, made on the first page with a form
+pub fn csrf_token<S>(req: &mut HttpRequest<S>) -> String {
+    if let Ok(Some(token)) = req.session().get::<String>(CSRF_KEY) {
+        return token;
+    }
+    let token = new_token();
+    if let Err(e) = req.session().set(CSRF_KEY, &token) {
+        eprintln!("Can not keep the CSRF token: {}", e);
+    }
+    token
+}
+
+// A new token, when the user logs in or out
+pub fn renew_csrf_token<S>(req: &mut HttpRequest<S>) {
+    req.session().remove(CSRF_KEY);
+}
+
+pub fn csrf_ok<S>(req: &mut HttpRequest<S>, sent: &str) -> bool {
+    match req.session().get::<String>(CSRF_KEY) {
+        Ok(Some(token)) => same(token.as_bytes(), sent.as_bytes()),
+        _ => false,
+    }
+}
+
+// Takes as long wherever the first difference is
+fn same(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+
+#[test]
+fn test_security_headers() {
+  use actix_web::test::TestRequest;
+
+  let mut req = TestRequest::default().finish();
+  let resp = match SecurityHeaders.response(&mut req, HttpResponse::Ok().finish()) {
+      Ok(Response::Done(resp)) => resp,
+      _ => panic!("the response is changed right away"),
+  };
+  let headers = resp.headers();
+  assert_eq!(headers["x-frame-options"], "DENY");
+  assert_eq!(headers["referrer-policy"], "same-origin");
+  assert!(headers["strict-transport-security"].to_str().unwrap().starts_with("max-age="));
+  let csp = headers["content-security-policy"].to_str().unwrap();
+  assert!(csp.contains("default-src 'self'"));
+  assert!(!csp.contains("unsafe-inline"));
+}
+
+#[test]
+fn test_tokens() {
+  let token = new_token();
+  assert_eq!(token.len(), 64);
+  assert_ne!(token, new_token());
+
+  assert!(same(token.as_bytes(), token.clone().as_bytes()));
+  assert!(!same(token.as_bytes(), new_token().as_bytes()));
+  assert!(!same(token.as_bytes(), b""));
+}
@@ -0,0 +1,198 @@
+// Integration tests: the whole app, middleware included, on a real server
+// on a random local port. Browser keeps the cookies between requests, so
+// the session and the flash go from one request to the next.
+
+use actix_web::client::ClientResponse;
+use actix_web::http::{header, Cookie, Method, StatusCode};
+use actix_web::test::TestServer;
+use actix_web::HttpMessage;
+use auth;
+use time::Duration;
+
+
+struct Browser {
+    srv: TestServer,
+    // name and value, in the order they were set
+    cookies: Vec<(String, String)>,
+}
+
+impl Browser {
+    fn new() -> Browser {
+        let srv = TestServer::with_factory(|| super::app(super::test_state(), &[0; auth::MIN_KEY_LEN]));
+        Browser { srv: srv, cookies: Vec::new() }
+    }
+
+    fn get(&mut self, path: &str) -> (ClientResponse, String) {
+        self.send(Method::GET, path, None)
+    }
+
+    fn post(&mut self, path: &str, form: &str) -> (ClientResponse, String) {
+        self.send(Method::POST, path, Some(form))
+    }
+
+    fn send(&mut self, method: Method, path: &str, form: Option<&str>) -> (ClientResponse, String) {
+        let mut request = self.srv.client(method, path);
+        if !self.cookies.is_empty() {
+            let cookies: Vec<String> = self.cookies.iter().map(|&(ref name, ref value)| format!("{}={}", name, value)).collect();
+            request.header(header::COOKIE, cookies.join("; "));
+        }
+        let request = match form {
+            Some(form) => request
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(form.to_string())
+                .unwrap(),
+            None => request.finish().unwrap(),
+        };
+        let response = self.srv.execute(request.send()).unwrap();
+        for value in response.headers().get_all(header::SET_COOKIE) {
+            self.keep(value.to_str().unwrap());
+        }
+        let body = self.srv.execute(response.body()).unwrap();
+        (response, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    // Like a browser: a new value replaces the old one, Max-Age=0 removes it
+    fn keep(&mut self, set_cookie: &str) {
+        let cookie = Cookie::parse(set_cookie.to_string()).unwrap();
+        self.cookies.retain(|&(ref name, _)| name != cookie.name());
+        if cookie.max_age() != Some(Duration::zero()) {
+            self.cookies.push((cookie.name().to_string(), cookie.value().to_string()));
+        }
+    }
+
+    fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref value)| value.as_str())
+    }
+}
+
+// The hidden csrf_token of the first form on the page
+fn csrf_token(page: &str) -> String {
+    let field = r#"name="csrf_token" value=""#;
+    let start = page.find(field).expect("a form with a csrf_token") + field.len();
+    page[start..].split('"').next().unwrap().to_string()
+}
+
+
+#[test]
+fn test_flash_is_shown_once() {
+  let mut browser = Browser::new();
+  let (_, page) = browser.get("/");
+  let form = format!("csrf_token={}&name=bruno&message=hello", csrf_token(&page));
+
+  let (response, _) = browser.post("/", &form);
+  assert_eq!(response.status(), StatusCode::SEE_OTHER);
+  assert_eq!(response.headers()["location"], "/");
+  assert_eq!(browser.cookie("flash"), Some("message-sent"));
+
+  let (response, page) = browser.get("/");
+  assert_eq!(response.status(), StatusCode::OK);
+  assert!(page.contains(r#"<p class="flash">Thanks, your message was sent.</p>"#));
+  assert_eq!(browser.cookie("flash"), None);
+
+  // a reload does not show it again
+  let (_, page) = browser.get("/");
+  assert!(!page.contains("class=\"flash\""));
+}
+
+#[test]
+fn test_methods_not_allowed() {
+  let mut browser = Browser::new();
+  let cases = [
+      (Method::POST, "/about", "GET"),
+      (Method::POST, "/links", "GET"),
+      (Method::DELETE, "/static/style.css", "GET"),
+      (Method::PUT, "/", "GET, POST"),
+      (Method::GET, "/logout", "POST"),
+  ];
+  for &(ref method, path, allow) in cases.iter() {
+      let (response, _) = browser.send(method.clone(), path, None);
+      assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, path);
+      assert_eq!(response.headers()["allow"], allow, "{} {}", method, path);
+  }
+  // the GET routes still answer
+  let (response, _) = browser.get("/static/style.css");
+  assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn test_login_and_logout() {
+  let mut browser = Browser::new();
+  let (_, page) = browser.get("/login");
+  let token = csrf_token(&page);
+
+  let (response, page) = browser.post("/login", &format!("csrf_token={}&name=bruno&password=wrong", token));
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  assert!(page.contains("Wrong name or password"));
+
+  let (response, _) = browser.post("/login", &format!("csrf_token={}&name=bruno&password=s3cret", token));
+  assert_eq!(response.status(), StatusCode::SEE_OTHER);
+  assert_eq!(response.headers()["location"], "/");
+  let session = browser.cookie("session").expect("a session cookie").to_string();
+
+  // the signed cookie is what keeps the user logged in
+  let (response, page) = browser.get("/");
+  assert_eq!(response.status(), StatusCode::OK);
+  assert!(page.contains("You are logged in."));
+  assert!(page.contains("Logged in as <strong>bruno</strong>"));
+  assert!(page.contains("<h1>Hi, bruno!</h1>"));
+  assert!(page.contains(r#"<form method="post" action="/">"#));
+  // a logged in user has nothing to do on the login page
+  let (response, _) = browser.get("/login");
+  assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+  // the token was renewed by the login
+  assert_ne!(csrf_token(&page), token);
+  let (response, _) = browser.post("/logout", &format!("csrf_token={}", csrf_token(&page)));
+  assert_eq!(response.status(), StatusCode::SEE_OTHER);
+  assert_ne!(browser.cookie("session"), Some(session.as_str()));
+
+  let (_, page) = browser.get("/");
+  assert!(page.contains("You are logged out."));
+  assert!(page.contains(r#"<a href="/login">Log in</a>"#));
+  assert!(!page.contains("Hi, bruno"));
+}
+
+#[test]
+fn test_forms_need_the_csrf_token() {
+  let forms = [
+      ("/", "name=bruno&message=hello"),
+      ("/login", "name=bruno&password=s3cret"),
+      ("/logout", ""),
+  ];
+  for &(path, fields) in forms.iter() {
+      let mut browser = Browser::new();
+      // without a session there is no token to match
+      let (response, _) = browser.post(path, &format!("csrf_token=&{}", fields));
+      assert_eq!(response.status(), StatusCode::FORBIDDEN, "POST {} before any page", path);
+
+      let (_, page) = browser.get("/login");
+      let token = csrf_token(&page);
+      let (response, page) = browser.post(path, fields);
+      assert_eq!(response.status(), StatusCode::FORBIDDEN, "POST {} without the token", path);
+      assert!(page.contains("403"), "POST {} without the token", path);
+      let (response, _) = browser.post(path, &format!("csrf_token={}x&{}", token, fields));
+      assert_eq!(response.status(), StatusCode::FORBIDDEN, "POST {} with a wrong token", path);
+
+      let (response, _) = browser.post(path, &format!("csrf_token={}&{}", token, fields));
+      assert_eq!(response.status(), StatusCode::SEE_OTHER, "POST {} with the token", path);
+  }
+}
+
+#[test]
+fn test_security_headers() {
+  let mut browser = Browser::new();
+  for path in &["/", "/static/style.css", "/nothing"] {
+      let (response, _) = browser.get(path);
+      let headers = response.headers();
+      assert_eq!(
+          headers["content-security-policy"],
+          "default-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'",
+          "GET {}",
+          path
+      );
+      assert_eq!(headers["x-frame-options"], "DENY", "GET {}", path);
+      assert_eq!(headers["referrer-policy"], "same-origin", "GET {}", path);
+      assert_eq!(headers["strict-transport-security"], "max-age=31536000; includeSubDomains", "GET {}", path);
+      assert_eq!(headers["x-content-type-options"], "nosniff", "GET {}", path);
+  }
+}
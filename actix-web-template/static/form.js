@@ -0,0 +1,10 @@
+// Characters left in the message, the server checks it again
+var message = document.getElementById('message');
+var count = document.getElementById('message-count');
+
+function update() {
+  count.textContent = count.dataset.text.replace('{ $count }', message.maxLength - message.value.length);
+}
+
+message.addEventListener('input', update);
+update();
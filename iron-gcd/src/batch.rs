@@ -0,0 +1,195 @@
+// Batch computation over an uploaded CSV
+//
+// Each input row is a list of integers, each output row is the same list
+// with the GCD and the LCM appended:
+//
+//   12,18        ->  12,18,6,36
+//   4, x         ->  ERROR,2,"value ""x"" is not a number"
+//
+// The upload is first copied to a temporary file, then the response is
+// written row by row while reading it back, so a large file is never
+// held in memory. A bad row is reported with its line number and the
+// following rows are still computed.
+//
+// The upload may come chunked, with no length, so the bytes are counted
+// while they are copied and the copy stops past `max_upload_bytes`.
+//
+// The body is written after the handler returns, so it holds the request
+// in shutdown.rs's count until the last row: a shutdown waits for it.
+
+use config::Limits;
+use iron::prelude::*;
+use iron::response::WriteBody;
+use limits::MaxUploadBytes;
+use multipart::server::Multipart;
+use number_theory::{gcd_list, lcm_list};
+use shutdown::{Counted, InFlight};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+use tempfile;
+
+
+// Quotes a CSV field, doubling the quotes inside it
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+// The output row for one input line, None for blank lines
+pub fn process_line(line_number: usize, line: &[u8]) -> Option<String> {
+    let error = |message: String| Some(format!("ERROR,{},{}", line_number, quote(&message)));
+
+    let line = match String::from_utf8(line.to_vec()) {
+        Err(_) => return error(String::from("row is not valid UTF-8")),
+        Ok(line) => line,
+    };
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let mut numbers = Vec::new();
+    for value in line.split(',').map(|value| value.trim()) {
+        match u64::from_str(value) {
+            Err(_) => return error(format!("value {:?} is not a number", value)),
+            Ok(n) => numbers.push(n),
+        }
+    }
+
+    let values: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    let lcm = match lcm_list(&numbers) {
+        None => String::from("overflow"),
+        Some(l) => l.to_string(),
+    };
+    Some(format!("{},{},{}", values.join(","), gcd_list(&numbers), lcm))
+}
+
+// The response body, computed while hyper writes it to the socket
+pub struct BatchBody<R> {
+    reader: R,
+    // dropped with the body, once it is written
+    _counted: Option<Counted>,
+}
+
+impl<R: BufRead + Send> WriteBody for BatchBody<R> {
+    fn write_body(&mut self, out: &mut Write) -> io::Result<()> {
+        let mut line = Vec::new();
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            line_number += 1;
+            if let Some(row) = process_line(line_number, &line) {
+                out.write_all(row.as_bytes())?;
+                out.write_all(b"\r\n")?;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    Io(io::Error),
+    // over this many bytes
+    TooLarge(u64),
+}
+
+impl From<io::Error> for UploadError {
+    fn from(e: io::Error) -> UploadError {
+        UploadError::Io(e)
+    }
+}
+
+fn spool<R: Read>(upload: R, max_bytes: u64) -> Result<BatchBody<BufReader<File>>, UploadError> {
+    let mut file = tempfile::tempfile()?;
+    // one byte more than allowed is enough to know it is too large
+    let copied = io::copy(&mut upload.take(max_bytes + 1), &mut file)?;
+    if copied > max_bytes {
+        return Err(UploadError::TooLarge(max_bytes));
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(BatchBody { reader: BufReader::new(file), _counted: None })
+}
+
+// The CSV is either the `file` field of a multipart/form-data form
+// or the whole request body: curl --data-binary @numbers.csv
+// Ok(None) when a multipart form has no `file` field
+pub fn read_upload(request: &mut Request) -> Result<Option<BatchBody<BufReader<File>>>, UploadError> {
+    // the handler is also called without the middleware in the tests
+    let max_bytes = match request.extensions.get::<MaxUploadBytes>() {
+        Some(&max_bytes) => max_bytes,
+        None => Limits::default().max_upload_bytes,
+    };
+    let counted = request.extensions.get::<InFlight>().map(InFlight::count);
+    let body = read_csv(request, max_bytes)?;
+    Ok(body.map(|body| BatchBody { _counted: counted, ..body }))
+}
+
+fn read_csv(request: &mut Request, max_bytes: u64) -> Result<Option<BatchBody<BufReader<File>>>, UploadError> {
+    match Multipart::from_request(request) {
+        Err(request) => spool(&mut request.body, max_bytes).map(Some),
+        Ok(mut multipart) => {
+            while let Some(field) = multipart.read_entry()? {
+                if &*field.headers.name == "file" {
+                    return spool(field.data, max_bytes).map(Some);
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+
+#[test]
+fn test_process_line() {
+  assert_eq!(process_line(1, b"12,18\n"), Some(String::from("12,18,6,36")));
+  assert_eq!(process_line(1, b" 4 , 6 ,8\r\n"), Some(String::from("4,6,8,2,24")));
+  assert_eq!(process_line(1, b"7"), Some(String::from("7,7,7")));
+  assert_eq!(process_line(1, b"0,5"), Some(String::from("0,5,5,0")));
+  assert_eq!(
+      process_line(1, b"18446744073709551615,18446744073709551614"),
+      Some(String::from("18446744073709551615,18446744073709551614,1,overflow"))
+  );
+  assert_eq!(process_line(1, b"  \r\n"), None);
+}
+
+#[test]
+fn test_process_bad_lines() {
+  assert_eq!(
+      process_line(2, b"4, x\n"),
+      Some(String::from(r#"ERROR,2,"value ""x"" is not a number""#))
+  );
+  assert_eq!(
+      process_line(3, b"4,,6"),
+      Some(String::from(r#"ERROR,3,"value """" is not a number""#))
+  );
+  assert_eq!(
+      process_line(4, b"\xff,1"),
+      Some(String::from(r#"ERROR,4,"row is not valid UTF-8""#))
+  );
+}
+
+#[test]
+fn test_batch_body_keeps_going_after_errors() {
+  let input: &[u8] = b"12,18\nnope\n\n24,81\n";
+  let mut body = BatchBody { reader: input, _counted: None };
+  let mut out = Vec::new();
+  body.write_body(&mut out).unwrap();
+  assert_eq!(
+      String::from_utf8(out).unwrap(),
+      "12,18,6,36\r\nERROR,2,\"value \"\"nope\"\" is not a number\"\r\n24,81,3,648\r\n"
+  );
+}
+
+#[test]
+fn test_upload_size_is_counted_while_reading() {
+  // like a chunked body, there is no length to check first
+  let upload: &[u8] = b"12,18\n24,81\n";
+  assert!(spool(upload, 12).is_ok());
+  match spool(upload, 11) {
+      Err(UploadError::TooLarge(11)) => (),
+      other => panic!("expected TooLarge, got {:?}", other.map(|_| ())),
+  }
+}
@@ -0,0 +1,97 @@
+// Arbitrary precision GCD for numbers that do not fit in a u64
+//
+// Euclid needs a division per step, which is expensive on big integers,
+// so here we use Stein's binary GCD: only shifts and subtractions.
+//   gcd(2u, 2v) = 2 * gcd(u, v)
+//   gcd(2u, v)  = gcd(u, v)       when v is odd
+//   gcd(u, v)   = gcd(u - v, v)   when both are odd and u >= v
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::cmp;
+use std::mem;
+
+
+// Number of zero bits at the right of `n`, 0 for n == 0.
+// Reads the digits in place, no copy of the number.
+fn trailing_zeros(n: &BigUint) -> usize {
+    n.trailing_zeros().unwrap_or(0) as usize
+}
+
+pub fn binary_gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    if a.is_zero() {
+        return b.clone();
+    }
+    if b.is_zero() {
+        return a.clone();
+    }
+
+    // the common power of 2 is put back at the end
+    let shift = cmp::min(trailing_zeros(a), trailing_zeros(b));
+
+    let mut u = a >> trailing_zeros(a);
+    let mut v = b.clone();
+
+    // from here on `u` is always odd
+    loop {
+        let zeros = trailing_zeros(&v);
+        v = v >> zeros;
+        if u > v {
+            mem::swap(&mut u, &mut v);
+        }
+        v = v - &u;
+        if v.is_zero() {
+            break;
+        }
+    }
+
+    u << shift
+}
+
+pub fn gcd_list(numbers: &[BigUint]) -> BigUint {
+    numbers.iter().fold(BigUint::zero(), |d, m| binary_gcd(&d, m))
+}
+
+
+#[test]
+fn test_binary_gcd() {
+  use std::str::FromStr;
+
+  let big = |s: &str| BigUint::from_str(s).unwrap();
+  assert_eq!(binary_gcd(&big("14"), &big("15")), big("1"));
+  assert_eq!(binary_gcd(&big("50"), &big("50")), big("50"));
+  assert_eq!(binary_gcd(&big("24"), &big("81")), big("3"));
+
+  // 2^200 * 3 and 2^150 * 9 share 2^150 * 3
+  let a = big("4820814132776970826625886277023487807566608981348378505904128");
+  let b = big("12845229234353638929524573725045456227444719616");
+  let expected = big("4281743078117879643174857908348485409148239872");
+  assert_eq!(binary_gcd(&a, &b), expected);
+}
+
+#[test]
+fn test_binary_gcd_matches_euclid() {
+  use number_theory::gcd;
+
+  for n in 0..200u64 {
+      for m in 0..200u64 {
+          let big = binary_gcd(&BigUint::from(n), &BigUint::from(m));
+          assert_eq!(big, BigUint::from(gcd(n, m)));
+      }
+  }
+
+  let max = u64::max_value();
+  assert_eq!(
+      binary_gcd(&BigUint::from(max), &BigUint::from(max - 1)),
+      BigUint::from(gcd(max, max - 1))
+  );
+}
+
+#[test]
+fn test_trailing_zeros() {
+  assert_eq!(trailing_zeros(&BigUint::zero()), 0);
+  assert_eq!(trailing_zeros(&BigUint::from(1u64)), 0);
+  assert_eq!(trailing_zeros(&BigUint::from(40u64)), 3);
+  // past the first digit
+  assert_eq!(trailing_zeros(&(BigUint::from(3u64) << 100)), 100);
+}
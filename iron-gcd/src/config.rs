@@ -0,0 +1,309 @@
+// Server configuration
+//
+// Each source overrides the previous one:
+//   defaults < config file (TOML) < environment variables < command line flags
+//
+// address = "0.0.0.0"
+// port = 3000
+// threads = 8
+// cert = "cert.pem"   # cert and key together enable HTTPS
+// key = "key.pem"
+// shutdown_timeout = 30         # seconds to finish the requests in flight
+//
+// [limits]
+// requests_per_second = 20.0   # per client IP, 0 turns the rate limit off
+// burst = 40
+// max_body_bytes = 1048576
+// max_upload_bytes = 67108864  # the CSV sent to /batch, counted as it is read
+// max_numbers = 1000           # values of a single field, like `n`
+
+use clap::{App, Arg, ArgMatches};
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+use toml;
+
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub address: String,
+    pub port: u16,
+    // None keeps Iron's default of 8 threads per CPU
+    pub threads: Option<usize>,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    // seconds, see shutdown.rs
+    pub shutdown_timeout: u64,
+    pub limits: Limits,
+}
+
+// See limits.rs
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub requests_per_second: f64,
+    // requests a client can send at once before being slowed down
+    pub burst: u32,
+    pub max_body_bytes: u64,
+    pub max_upload_bytes: u64,
+    pub max_numbers: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            requests_per_second: 20.0,
+            burst: 40,
+            max_body_bytes: 1024 * 1024,
+            max_upload_bytes: 64 * 1024 * 1024,
+            max_numbers: 1000,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            address: String::from("localhost"),
+            port: 3000,
+            threads: None,
+            cert: None,
+            key: None,
+            shutdown_timeout: 30,
+            limits: Limits::default(),
+        }
+    }
+}
+
+fn cli<'a, 'b>() -> App<'a, 'b> {
+    App::new("iron-gcd")
+        .about("Number theory calculator over HTTP")
+        .arg(Arg::with_name("config")
+            .long("config")
+            .short("c")
+            .takes_value(true)
+            .help("TOML config file [env: GCD_CONFIG]"))
+        .arg(Arg::with_name("address")
+            .long("address")
+            .short("a")
+            .takes_value(true)
+            .help("Address to bind [env: GCD_ADDRESS] [default: localhost]"))
+        .arg(Arg::with_name("port")
+            .long("port")
+            .short("p")
+            .takes_value(true)
+            .help("Port to listen on [env: GCD_PORT] [default: 3000]"))
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .short("t")
+            .takes_value(true)
+            .help("Number of worker threads [env: GCD_THREADS]"))
+        .arg(Arg::with_name("cert")
+            .long("cert")
+            .takes_value(true)
+            .requires("key")
+            .help("PEM certificate, enables HTTPS [env: GCD_CERT]"))
+        .arg(Arg::with_name("key")
+            .long("key")
+            .takes_value(true)
+            .requires("cert")
+            .help("PEM private key for --cert [env: GCD_KEY]"))
+        .arg(Arg::with_name("shutdown-timeout")
+            .long("shutdown-timeout")
+            .takes_value(true)
+            .help("Seconds to finish the requests in flight on shutdown [env: GCD_SHUTDOWN_TIMEOUT] [default: 30]"))
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    T::from_str(value).map_err(|_| format!("Invalid value for {}: {:?}", name, value))
+}
+
+impl Config {
+    pub fn from_toml(source: &str) -> Result<Config, String> {
+        toml::from_str(source).map_err(|e| format!("Invalid config file: {}", e))
+    }
+
+    pub fn from_file(path: &str) -> Result<Config, String> {
+        let mut source = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|e| format!("Can not read config file {}: {}", path, e))?;
+        Config::from_toml(&source)
+    }
+
+    // `var` looks up an environment variable, it is a parameter
+    // so tests do not need to touch the real environment
+    fn apply_env<F: Fn(&str) -> Option<String>>(&mut self, var: F) -> Result<(), String> {
+        if let Some(address) = var("GCD_ADDRESS") {
+            self.address = address;
+        }
+        if let Some(port) = var("GCD_PORT") {
+            self.port = parse("GCD_PORT", &port)?;
+        }
+        if let Some(threads) = var("GCD_THREADS") {
+            self.threads = Some(parse("GCD_THREADS", &threads)?);
+        }
+        if let Some(cert) = var("GCD_CERT") {
+            self.cert = Some(PathBuf::from(cert));
+        }
+        if let Some(key) = var("GCD_KEY") {
+            self.key = Some(PathBuf::from(key));
+        }
+        if let Some(timeout) = var("GCD_SHUTDOWN_TIMEOUT") {
+            self.shutdown_timeout = parse("GCD_SHUTDOWN_TIMEOUT", &timeout)?;
+        }
+        if let Some(rate) = var("GCD_RATE_LIMIT") {
+            self.limits.requests_per_second = parse("GCD_RATE_LIMIT", &rate)?;
+        }
+        if let Some(burst) = var("GCD_BURST") {
+            self.limits.burst = parse("GCD_BURST", &burst)?;
+        }
+        if let Some(bytes) = var("GCD_MAX_BODY_BYTES") {
+            self.limits.max_body_bytes = parse("GCD_MAX_BODY_BYTES", &bytes)?;
+        }
+        if let Some(bytes) = var("GCD_MAX_UPLOAD_BYTES") {
+            self.limits.max_upload_bytes = parse("GCD_MAX_UPLOAD_BYTES", &bytes)?;
+        }
+        if let Some(numbers) = var("GCD_MAX_NUMBERS") {
+            self.limits.max_numbers = parse("GCD_MAX_NUMBERS", &numbers)?;
+        }
+        Ok(())
+    }
+
+    fn apply_args(&mut self, matches: &ArgMatches) -> Result<(), String> {
+        if let Some(address) = matches.value_of("address") {
+            self.address = address.to_string();
+        }
+        if let Some(port) = matches.value_of("port") {
+            self.port = parse("--port", port)?;
+        }
+        if let Some(threads) = matches.value_of("threads") {
+            self.threads = Some(parse("--threads", threads)?);
+        }
+        if let Some(cert) = matches.value_of("cert") {
+            self.cert = Some(PathBuf::from(cert));
+        }
+        if let Some(key) = matches.value_of("key") {
+            self.key = Some(PathBuf::from(key));
+        }
+        if let Some(timeout) = matches.value_of("shutdown-timeout") {
+            self.shutdown_timeout = parse("--shutdown-timeout", timeout)?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.threads == Some(0) {
+            return Err(String::from("threads must be at least 1"));
+        }
+        if self.cert.is_some() != self.key.is_some() {
+            return Err(String::from("HTTPS needs both a cert and a key"));
+        }
+        let limits = &self.limits;
+        if !(limits.requests_per_second >= 0.0 && limits.requests_per_second.is_finite()) {
+            return Err(String::from("requests_per_second must be a positive number or 0"));
+        }
+        if limits.requests_per_second > 0.0 && limits.burst == 0 {
+            return Err(String::from("burst must be at least 1"));
+        }
+        if limits.max_numbers == 0 {
+            return Err(String::from("max_numbers must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+fn load_from<F>(matches: &ArgMatches, var: F) -> Result<Config, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let path = matches
+        .value_of("config")
+        .map(String::from)
+        .or_else(|| var("GCD_CONFIG"));
+
+    let mut config = match path {
+        Some(path) => Config::from_file(&path)?,
+        None => Config::default(),
+    };
+    config.apply_env(&var)?;
+    config.apply_args(matches)?;
+    config.validate()?;
+    Ok(config)
+}
+
+pub fn load() -> Result<Config, String> {
+    load_from(&cli().get_matches(), |name| env::var(name).ok())
+}
+
+
+#[cfg(test)]
+fn no_env(_: &str) -> Option<String> {
+    None
+}
+
+#[test]
+fn test_defaults() {
+  let matches = cli().get_matches_from(vec!["iron-gcd"]);
+  assert_eq!(load_from(&matches, no_env).unwrap(), Config::default());
+}
+
+#[test]
+fn test_from_toml() {
+  let config = Config::from_toml("address = \"0.0.0.0\"\nthreads = 4\n").unwrap();
+  assert_eq!(config.address, "0.0.0.0");
+  assert_eq!(config.port, 3000);
+  assert_eq!(config.threads, Some(4));
+  assert!(Config::from_toml("prot = 80").is_err());
+
+  let config = Config::from_toml("port = 80\n[limits]\nburst = 5\nmax_numbers = 10\n").unwrap();
+  assert_eq!(config.limits.burst, 5);
+  assert_eq!(config.limits.max_numbers, 10);
+  assert_eq!(config.limits.max_body_bytes, Limits::default().max_body_bytes);
+  assert!(Config::from_toml("[limits]\nburts = 5").is_err());
+}
+
+#[test]
+fn test_precedence() {
+  let mut config = Config::from_toml("port = 8000\nthreads = 4").unwrap();
+
+  config.apply_env(|name| match name {
+      "GCD_PORT" => Some(String::from("9000")),
+      "GCD_ADDRESS" => Some(String::from("0.0.0.0")),
+      _ => None,
+  }).unwrap();
+  assert_eq!((config.address.as_str(), config.port, config.threads), ("0.0.0.0", 9000, Some(4)));
+
+  let matches = cli().get_matches_from(vec!["iron-gcd", "--port", "443", "--cert", "c.pem", "--key", "k.pem", "--shutdown-timeout", "5"]);
+  config.apply_args(&matches).unwrap();
+  assert_eq!(config.port, 443);
+  assert_eq!(config.shutdown_timeout, 5);
+  assert_eq!(config.cert, Some(PathBuf::from("c.pem")));
+}
+
+#[test]
+fn test_invalid_values() {
+  let matches = cli().get_matches_from(vec!["iron-gcd", "--port", "http"]);
+  assert!(load_from(&matches, no_env).is_err());
+
+  let matches = cli().get_matches_from(vec!["iron-gcd", "--threads", "0"]);
+  assert!(load_from(&matches, no_env).is_err());
+
+  let matches = cli().get_matches_from(vec!["iron-gcd"]);
+  let cert_only = |name: &str| if name == "GCD_CERT" { Some(String::from("c.pem")) } else { None };
+  assert!(load_from(&matches, cert_only).is_err());
+
+  for &(name, value) in &[("GCD_RATE_LIMIT", "-1"), ("GCD_RATE_LIMIT", "NaN"), ("GCD_BURST", "0"), ("GCD_MAX_NUMBERS", "0")] {
+      let env = |var: &str| if var == name { Some(String::from(value)) } else { None };
+      assert!(load_from(&matches, env).is_err(), "{}={}", name, value);
+  }
+  let no_rate_limit = |var: &str| match var {
+      "GCD_RATE_LIMIT" => Some(String::from("0")),
+      "GCD_BURST" => Some(String::from("0")),
+      _ => None,
+  };
+  assert!(load_from(&matches, no_rate_limit).is_ok());
+}
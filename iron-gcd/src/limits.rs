@@ -0,0 +1,268 @@
+// Protection against clients sending too much, configured in config.rs
+//
+// RateLimit gives every client IP a token bucket: each request takes a
+// token and tokens come back at `requests_per_second`, up to `burst`.
+// A client with an empty bucket gets 429 Too Many Requests.
+//
+// BodyLimit answers 413 Payload Too Large before anything reads a body
+// over `max_body_bytes`, and stores `max_numbers` in the request
+// extensions for the form parsing to enforce.
+// Uploads (/batch) have their own `max_upload_bytes` and may be sent
+// chunked, without a Content-Length: batch.rs counts the bytes as it
+// reads them, the limit is in the request extensions.
+
+use config::Limits;
+use iron::headers::ContentLength;
+use iron::method::Method;
+use iron::prelude::*;
+use iron::typemap::Key;
+use iron::{status, BeforeMiddleware};
+use middleware::{path, seconds};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+
+// when this many clients are tracked the idle ones are forgotten
+const MAX_CLIENTS: usize = 10_000;
+// and when none is idle, this many of the least recently seen
+const EVICTED_CLIENTS: usize = MAX_CLIENTS / 10;
+
+#[derive(Debug)]
+pub struct LimitExceeded(String);
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for LimitExceeded {
+    fn description(&self) -> &str {
+        &self.0
+    }
+}
+
+fn refuse(status: status::Status, message: String) -> IronError {
+    let body = format!("{}\n", message);
+    IronError::new(LimitExceeded(message), (status, body))
+}
+
+
+// --- Rate limit ---
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant, rate: f64, burst: f64) -> f64 {
+        let tokens = self.tokens + seconds(now.duration_since(self.updated)) * rate;
+        tokens.min(burst)
+    }
+}
+
+pub struct RateLimit {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimit {
+    pub fn new(limits: &Limits) -> RateLimit {
+        RateLimit {
+            rate: limits.requests_per_second,
+            burst: limits.burst as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Err holds the seconds to wait for the next token
+    fn take(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        let (rate, burst) = (self.rate, self.burst);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&ip) {
+            // a full bucket is the same as no bucket
+            buckets.retain(|_, bucket| bucket.refilled(now, rate, burst) < burst);
+            if buckets.len() >= MAX_CLIENTS {
+                // every client is active: a batch of the oldest goes, so
+                // the next new clients find room without another scan
+                let mut seen: Vec<(Instant, IpAddr)> =
+                    buckets.iter().map(|(ip, bucket)| (bucket.updated, *ip)).collect();
+                seen.sort();
+                for &(_, ip) in seen.iter().take(EVICTED_CLIENTS) {
+                    buckets.remove(&ip);
+                }
+            }
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: burst, updated: now });
+        bucket.tokens = bucket.refilled(now, rate, burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / rate).ceil() as u64)
+        }
+    }
+}
+
+impl BeforeMiddleware for RateLimit {
+    fn before(&self, request: &mut Request) -> IronResult<()> {
+        if self.rate <= 0.0 {
+            return Ok(());
+        }
+        match self.take(request.remote_addr.ip(), Instant::now()) {
+            Ok(()) => Ok(()),
+            Err(wait) => {
+                let mut err = refuse(status::TooManyRequests, String::from("Too many requests, slow down"));
+                err.response.headers.set_raw("Retry-After", vec![wait.to_string().into_bytes()]);
+                Err(err)
+            }
+        }
+    }
+}
+
+
+// --- Body size ---
+
+pub struct MaxNumbers;
+
+impl Key for MaxNumbers {
+    type Value = usize;
+}
+
+pub struct MaxUploadBytes;
+
+impl Key for MaxUploadBytes {
+    type Value = u64;
+}
+
+pub struct BodyLimit {
+    max_body_bytes: u64,
+    max_upload_bytes: u64,
+    max_numbers: usize,
+    // the paths that take uploads
+    uploads: Vec<&'static str>,
+}
+
+impl BodyLimit {
+    pub fn new(limits: &Limits, uploads: &[&'static str]) -> BodyLimit {
+        BodyLimit {
+            max_body_bytes: limits.max_body_bytes,
+            max_upload_bytes: limits.max_upload_bytes,
+            max_numbers: limits.max_numbers,
+            uploads: uploads.to_vec(),
+        }
+    }
+}
+
+pub fn body_too_large(max_bytes: u64) -> String {
+    format!("Request body is larger than {} bytes", max_bytes)
+}
+
+impl BeforeMiddleware for BodyLimit {
+    fn before(&self, request: &mut Request) -> IronResult<()> {
+        request.extensions.insert::<MaxNumbers>(self.max_numbers);
+
+        if self.uploads.contains(&path(request).as_str()) {
+            request.extensions.insert::<MaxUploadBytes>(self.max_upload_bytes);
+            return match request.headers.get::<ContentLength>() {
+                Some(&ContentLength(length)) if length > self.max_upload_bytes => {
+                    Err(refuse(status::PayloadTooLarge, body_too_large(self.max_upload_bytes)))
+                }
+                _ => Ok(()),
+            };
+        }
+
+        match request.headers.get::<ContentLength>() {
+            Some(&ContentLength(length)) if length > self.max_body_bytes => {
+                Err(refuse(status::PayloadTooLarge, body_too_large(self.max_body_bytes)))
+            }
+            Some(_) => Ok(()),
+            // a chunked body could be of any size, browsers and curl
+            // always send the length of a form
+            None if request.method == Method::Post => Err(refuse(
+                status::LengthRequired,
+                String::from("Content-Length is required"),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+fn limiter(requests_per_second: f64, burst: u32) -> RateLimit {
+    RateLimit::new(&Limits { requests_per_second, burst, ..Limits::default() })
+}
+
+#[test]
+fn test_token_bucket() {
+  use std::time::Duration;
+
+  let limiter = limiter(2.0, 3);
+  let client: IpAddr = "10.0.0.1".parse().unwrap();
+  let other: IpAddr = "10.0.0.2".parse().unwrap();
+  let start = Instant::now();
+
+  for _ in 0..3 {
+      assert_eq!(limiter.take(client, start), Ok(()));
+  }
+  assert_eq!(limiter.take(client, start), Err(1));
+  // every client has its own bucket
+  assert_eq!(limiter.take(other, start), Ok(()));
+
+  // one token every half second, never more than the burst
+  assert_eq!(limiter.take(client, start + Duration::from_millis(500)), Ok(()));
+  assert_eq!(limiter.take(client, start + Duration::from_millis(500)), Err(1));
+  let later = start + Duration::from_secs(60);
+  for _ in 0..3 {
+      assert_eq!(limiter.take(client, later), Ok(()));
+  }
+  assert!(limiter.take(client, later).is_err());
+}
+
+#[test]
+fn test_idle_clients_are_forgotten() {
+  use std::net::Ipv4Addr;
+  use std::time::Duration;
+
+  let limiter = limiter(1.0, 1);
+  let start = Instant::now();
+  for i in 0..MAX_CLIENTS as u32 {
+      let _ = limiter.take(IpAddr::V4(Ipv4Addr::from(i)), start);
+  }
+  let later = start + Duration::from_secs(2);
+  assert_eq!(limiter.take("10.255.0.1".parse().unwrap(), later), Ok(()));
+  assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_oldest_clients_go_when_all_are_active() {
+  use std::net::Ipv4Addr;
+  use std::time::Duration;
+
+  // nobody gets back to a full bucket in the test
+  let limiter = limiter(0.001, 2);
+  let start = Instant::now();
+  let client = |i: usize| IpAddr::V4(Ipv4Addr::from(i as u32));
+  for i in 0..MAX_CLIENTS {
+      assert_eq!(limiter.take(client(i), start + Duration::from_millis(i as u64)), Ok(()));
+  }
+
+  let later = start + Duration::from_millis(MAX_CLIENTS as u64);
+  assert_eq!(limiter.take("10.255.0.1".parse().unwrap(), later), Ok(()));
+  let buckets = limiter.buckets.lock().unwrap();
+  assert_eq!(buckets.len(), MAX_CLIENTS - EVICTED_CLIENTS + 1);
+  assert!(!buckets.contains_key(&client(0)));
+  assert!(!buckets.contains_key(&client(EVICTED_CLIENTS - 1)));
+  assert!(buckets.contains_key(&client(EVICTED_CLIENTS)));
+  assert!(buckets.contains_key(&client(MAX_CLIENTS - 1)));
+}
@@ -1,111 +1,780 @@
+#[macro_use]
+extern crate askama;
+extern crate chan_signal;
+extern crate clap;
+extern crate hyper_openssl;
 extern crate iron;
+extern crate multipart;
+extern crate num_bigint;
+extern crate num_traits;
 extern crate router;
+extern crate serde;
+extern crate serde_json;
+extern crate tempfile;
+extern crate toml;
 extern crate urlencoded;
 #[macro_use]
 extern crate mime;
+#[macro_use]
+extern crate serde_derive;
+
+#[cfg(test)]
+extern crate iron_test;
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck;
 
+mod batch;
+mod bigint;
+mod config;
+mod limits;
+mod middleware;
+mod number_theory;
+mod pages;
+mod session;
+mod shutdown;
+#[cfg(test)]
+mod tests;
+
+use askama::Template;
+use batch::UploadError;
+use chan_signal::Signal;
+use config::Limits;
+use hyper_openssl::OpensslServer;
+use iron::mime::Mime;
 use iron::prelude::*;
 use iron::status;
+use iron::AroundMiddleware;
+use limits::{BodyLimit, MaxNumbers, RateLimit};
+use middleware::{AccessLog, Metrics, RequestId};
+use num_bigint::BigUint;
+use pages::{Answer, Batch, Calculator, Field, Operation};
+use number_theory::{extended_gcd, factorize, gcd_list, gcd_list_steps, lcm_list, mod_inverse,
+                    Reduction};
 use router::Router;
+use session::History;
+use shutdown::InFlight;
+use std::process;
 use std::str::FromStr;
+use std::time::Duration;
 use urlencoded::UrlEncodedBody;
 
 
 fn main() {
+    let config = match config::load() {
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Ok(config) => config,
+    };
+
+    // must be called before any other thread is spawned
+    // so the signals are delivered to this channel only
+    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM]);
+
+    let in_flight = InFlight::new();
+    let mut iron = Iron::new(in_flight.clone().around(Box::new(app(&config.limits))));
+    if let Some(threads) = config.threads {
+        iron.threads = threads;
+    }
+
+    let address = (config.address.as_str(), config.port);
+    let listening = match (&config.cert, &config.key) {
+        (&Some(ref cert), &Some(ref key)) => {
+            let ssl = match OpensslServer::from_files(key, cert) {
+                Err(e) => {
+                    eprintln!("Can not load the certificate: {}", e);
+                    process::exit(1);
+                }
+                Ok(ssl) => ssl,
+            };
+            println!("Serving on https://{}:{}", config.address, config.port);
+            iron.https(address, ssl)
+        }
+        _ => {
+            println!("Serving on http://{}:{}", config.address, config.port);
+            iron.http(address)
+        }
+    };
+
+    let _listening = match listening {
+        Err(e) => {
+            eprintln!("Can not listen on {}:{}: {}", config.address, config.port, e);
+            process::exit(1);
+        }
+        Ok(listening) => listening,
+    };
+
+    // block until SIGINT or SIGTERM (docker stop) arrives, then let the
+    // requests in flight finish, see shutdown.rs
+    if let Some(signal) = signal.recv() {
+        println!("Received {:?}, shutting down", signal);
+    }
+    let drained = in_flight.drain(Duration::from_secs(config.shutdown_timeout));
+    if !drained {
+        eprintln!("{} requests still running after {}s, exiting anyway", in_flight.requests(), config.shutdown_timeout);
+    }
+    // not a return: dropping `_listening` joins the accept thread,
+    // which never ends
+    process::exit(if drained { 0 } else { 1 });
+}
+
+// The router wrapped in the middleware chain, see middleware.rs
+fn app(limits: &Limits) -> Chain {
+    let metrics = Metrics::new(&[
+        "/", "/gcd", "/api/gcd", "/lcm", "/egcd", "/modinv", "/factor", "/batch", "/metrics",
+    ]);
     let mut router = Router::new();
 
     router.get("/", get_form, "root");
     router.post("/gcd", post_gcd, "gcd");
+    router.post("/api/gcd", post_api_gcd, "api_gcd");
+    router.get("/lcm", get_lcm_form, "lcm_form");
+    router.post("/lcm", post_lcm, "lcm");
+    router.get("/egcd", get_egcd_form, "egcd_form");
+    router.post("/egcd", post_egcd, "egcd");
+    router.get("/modinv", get_modinv_form, "modinv_form");
+    router.post("/modinv", post_modinv, "modinv");
+    router.get("/factor", get_factor_form, "factor_form");
+    router.post("/factor", post_factor, "factor");
+    router.get("/batch", get_batch_form, "batch_form");
+    router.post("/batch", post_batch, "batch");
+    router.get("/metrics", metrics.handler(), "metrics");
 
-    println!("Serving on http://localhost:3000");
-    Iron::new(router).http("localhost:3000").unwrap();
+    let mut chain = Chain::new(router);
+    chain.link_before(RequestId::new());
+    chain.link_before(RateLimit::new(limits));
+    chain.link_before(BodyLimit::new(limits, &["/batch"]));
+    chain.link_after(metrics);
+    chain.link_after(AccessLog);
+    chain
 }
 
 
-fn gcd(mut n: u64, mut m: u64) -> u64 {
-    assert!(n != 0 && m != 0);
-    while m != 0 {
-        if m < n {
-            let t = m;
-            m = n;
-            n = t;
-        }
-        m = m % n;
-    }
-    n
-}
+// --- Response helpers ---
 
-fn get_form(_request: &mut Request) -> IronResult<Response> {
+fn html(body: String) -> Response {
     let mut response = Response::new();
-
     response.set_mut(status::Ok);
     response.set_mut(mime!(Text/Html; Charset=Utf8));
-    response.set_mut(
-        r#"
-    <title>GCD Calculator</title>
-    <form action="/gcd" method="post">
-      <input type="text" name="n"/>
-      <input type="text" name="n"/>
-      <button type="submit">Compute GCD</button>
-    </form>
-  "#,
-    );
+    response.set_mut(body);
+    response
+}
 
-    Ok(response)
+fn json<T: serde::Serialize>(value: &T) -> Response {
+    let mut response = Response::new();
+    response.set_mut(status::Ok);
+    response.set_mut(mime!(Application/Json));
+    response.set_mut(serde_json::to_string(value).unwrap());
+    response
 }
 
-fn post_gcd(request: &mut Request) -> IronResult<Response> {
+// Why a request could not be answered and the status to reply with
+// the html pages show the message next to the form, the API wraps it
+// in JSON and the CSV upload answers with plain text
+type Failure = (status::Status, String);
+
+// 400 is for requests we can not read: malformed body, missing fields
+// or values that are not numbers
+fn bad_request(message: String) -> Failure {
+    (status::BadRequest, message)
+}
+
+// 413 is for requests over the configured limits, see limits.rs
+fn too_large(message: String) -> Failure {
+    (status::PayloadTooLarge, message)
+}
+
+// 422 is for well formed numbers we can not compute an answer for
+fn unprocessable(message: String) -> Failure {
+    (status::UnprocessableEntity, message)
+}
+
+fn text_error((status, message): Failure) -> Response {
     let mut response = Response::new();
+    response.set_mut(status);
+    response.set_mut(message);
+    response
+}
+
+#[derive(Serialize)]
+struct ApiError {
+    error: String,
+}
+
+fn json_error((status, message): Failure) -> Response {
+    let mut response = json(&ApiError { error: message.trim().to_string() });
+    response.set_mut(status);
+    response
+}
+
+// --- Form parsing ---
+
+// Parses every value sent for `name` as a T (u64 or BigUint)
+fn parse_numbers<T: FromStr>(request: &mut Request, name: &str) -> Result<Vec<T>, Failure> {
+    // the handlers are also called without the middleware in the tests
+    let max_numbers = match request.extensions.get::<MaxNumbers>() {
+        Some(&max_numbers) => max_numbers,
+        None => Limits::default().max_numbers,
+    };
 
     let form_data = match request.get_ref::<UrlEncodedBody>() {
-        Err(e) => {
-            response.set_mut(status::BadRequest);
-            response.set_mut(format!("Error parsing from data {:?}\n", e));
-            return Ok(response);
-        }
+        Err(e) => return Err(bad_request(format!("Error parsing form data {:?}\n", e))),
         Ok(map) => map,
     };
 
-    let unparsed_numbers = match form_data.get("n") {
-        None => {
-            response.set_mut(status::BadRequest);
-            response.set_mut(format!("form data has no 'n' paramater\n"));
-            return Ok(response);
-        }
+    let unparsed_numbers = match form_data.get(name) {
+        None => return Err(bad_request(format!("form data has no '{}' parameter\n", name))),
         Some(nums) => nums,
     };
+    if unparsed_numbers.len() > max_numbers {
+        return Err(too_large(format!(
+            "Too many values for '{}', at most {} are accepted\n",
+            name,
+            max_numbers
+        )));
+    }
 
     let mut numbers = Vec::new();
     for unparsed in unparsed_numbers {
-        match u64::from_str(&unparsed) {
+        match T::from_str(&unparsed) {
             Err(_) => {
-                response.set_mut(status::BadRequest);
-                response.set_mut(format!("Value for 'n' is not a number: {:?}\n", unparsed));
-                return Ok(response);
+                return Err(bad_request(format!(
+                    "Value for '{}' is not a number: {:?}\n",
+                    name,
+                    unparsed
+                )))
             }
             Ok(n) => numbers.push(n),
         }
     }
 
-    let mut d = numbers[0];
-    for m in &numbers[1..] {
-        d = gcd(d, *m);
+    if numbers.is_empty() {
+        return Err(unprocessable(format!("No values given for '{}'\n", name)));
     }
+    Ok(numbers)
+}
 
-    response.set_mut(status::Ok);
-    response.set_mut(mime!(Text/Html; Charset=Utf8));
-    response.set_mut(format!(
-        "The greatest common divisor of the numbers {:?} is <b>{}</b>\n",
+// A checkbox is only sent when it is checked
+fn has_field(request: &mut Request, name: &str) -> bool {
+    match request.get_ref::<UrlEncodedBody>() {
+        Err(_) => false,
+        Ok(map) => map.contains_key(name),
+    }
+}
+
+fn parse_number(request: &mut Request, name: &str) -> Result<u64, Failure> {
+    let numbers: Vec<u64> = parse_numbers(request, name)?;
+    if numbers.len() != 1 {
+        return Err(bad_request(format!("Expected exactly one value for '{}'\n", name)));
+    }
+    Ok(numbers[0])
+}
+
+// The raw values sent for each field, so the form shows what was typed
+// even when it is not a number. Missing fields are left empty.
+fn submitted_fields(request: &mut Request, operation: &Operation) -> Vec<Field> {
+    let form_data = request.get_ref::<UrlEncodedBody>().ok();
+
+    let mut fields = Vec::new();
+    for (i, &name) in operation.fields.iter().enumerate() {
+        // repeated names are handled on their first occurrence
+        if operation.fields[..i].contains(&name) {
+            continue;
+        }
+        let expected = operation.fields.iter().filter(|field| **field == name).count();
+        let mut values = form_data
+            .and_then(|map| map.get(name))
+            .cloned()
+            .unwrap_or_else(Vec::new);
+        while values.len() < expected {
+            values.push(String::new());
+        }
+        for value in values {
+            fields.push(Field {
+                name,
+                value,
+                repeatable: operation.is_repeatable(name),
+            });
+        }
+    }
+    fields
+}
+
+
+// --- Pages ---
+
+fn render(page: &Calculator, status: status::Status) -> Response {
+    let mut response = html(page.render().unwrap());
+    response.set_mut(status);
+    response
+}
+
+fn show_form(operation: &Operation, request: &mut Request) -> Response {
+    let history = History::load(request);
+    let page = Calculator::new(operation, submitted_fields(request, operation), &history.entries);
+    render(&page, status::Ok)
+}
+
+// Runs `compute` and renders the result, or the error, below the form.
+// Successful calculations are added to the history cookie.
+fn show_answer(
+    operation: &Operation,
+    request: &mut Request,
+    compute: fn(&mut Request) -> Result<Answer, Failure>,
+) -> Response {
+    let mut history = History::load(request);
+    let result = compute(request);
+
+    let fields = submitted_fields(request, operation);
+    if let Ok(ref answer) = result {
+        let input: Vec<&str> = fields.iter().map(|field| field.value.as_str()).collect();
+        history.push(operation.name, &input.join(", "), &answer.value);
+    }
+
+    let mut page = Calculator::new(operation, fields, &history.entries);
+    for checkbox in &mut page.checkboxes {
+        checkbox.checked = has_field(request, checkbox.name);
+    }
+
+    match result {
+        Err((status, message)) => {
+            page.error = Some(message);
+            render(&page, status)
+        }
+        Ok(answer) => {
+            page.answer = Some(answer);
+            let mut response = render(&page, status::Ok);
+            history.save(&mut response);
+            response
+        }
+    }
+}
+
+fn answer(text: String, value: String) -> Answer {
+    Answer {
+        text,
+        value,
+        reductions: Vec::new(),
+    }
+}
+
+
+// --- GCD ---
+
+fn get_form(request: &mut Request) -> IronResult<Response> {
+    Ok(show_form(&pages::GCD, request))
+}
+
+fn post_gcd(request: &mut Request) -> IronResult<Response> {
+    Ok(show_answer(&pages::GCD, request, compute_gcd))
+}
+
+fn compute_gcd(request: &mut Request) -> Result<Answer, Failure> {
+    if has_field(request, "big") {
+        return compute_big_gcd(request);
+    }
+
+    let numbers: Vec<u64> = parse_numbers(request, "n")?;
+
+    let mut answer = answer(
+        format!("The greatest common divisor of the numbers {:?} is", numbers),
+        gcd_list(&numbers).to_string(),
+    );
+    if has_field(request, "trace") {
+        answer.reductions = gcd_list_steps(&numbers);
+    }
+    Ok(answer)
+}
+
+#[derive(Serialize)]
+struct GcdResult {
+    numbers: Vec<u64>,
+    gcd: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<Vec<Reduction>>,
+}
+
+// Big integers do not fit in JSON numbers, they are sent as strings
+#[derive(Serialize)]
+struct BigGcdResult {
+    numbers: Vec<String>,
+    gcd: String,
+}
+
+// Same form fields as /gcd, answered with JSON
+fn post_api_gcd(request: &mut Request) -> IronResult<Response> {
+    if has_field(request, "big") {
+        return Ok(match api_big_gcd(request) {
+            Err(failure) => json_error(failure),
+            Ok(result) => json(&result),
+        });
+    }
+
+    let numbers: Vec<u64> = match parse_numbers(request, "n") {
+        Err(failure) => return Ok(json_error(failure)),
+        Ok(numbers) => numbers,
+    };
+
+    let trace = if has_field(request, "trace") {
+        Some(gcd_list_steps(&numbers))
+    } else {
+        None
+    };
+
+    Ok(json(&GcdResult {
+        gcd: gcd_list(&numbers),
         numbers,
-        d
-    ));
+        trace,
+    }))
+}
+
+// big integer mode uses Stein's algorithm so there is no Euclid trace for it
+fn api_big_gcd(request: &mut Request) -> Result<BigGcdResult, Failure> {
+    let numbers: Vec<BigUint> = parse_numbers(request, "n")?;
+    if has_field(request, "trace") {
+        return Err(unprocessable(String::from("There is no trace in big integer mode")));
+    }
+    Ok(BigGcdResult {
+        gcd: bigint::gcd_list(&numbers).to_string(),
+        numbers: numbers.iter().map(|n| n.to_string()).collect(),
+    })
+}
+
+// In big integer mode the values are not limited to 64 bits
+fn compute_big_gcd(request: &mut Request) -> Result<Answer, Failure> {
+    let numbers: Vec<BigUint> = parse_numbers(request, "n")?;
+
+    let d = bigint::gcd_list(&numbers);
+
+    let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    Ok(answer(
+        format!("The greatest common divisor of the numbers {:?} is", numbers),
+        d.to_string(),
+    ))
+}
+
+
+// --- LCM ---
+
+fn get_lcm_form(request: &mut Request) -> IronResult<Response> {
+    Ok(show_form(&pages::LCM, request))
+}
+
+fn post_lcm(request: &mut Request) -> IronResult<Response> {
+    Ok(show_answer(&pages::LCM, request, compute_lcm))
+}
+
+fn compute_lcm(request: &mut Request) -> Result<Answer, Failure> {
+    let numbers: Vec<u64> = parse_numbers(request, "n")?;
+
+    match lcm_list(&numbers) {
+        None => Err(unprocessable(format!(
+            "The least common multiple of the numbers {:?} does not fit in 64 bits\n",
+            numbers
+        ))),
+        Some(l) => Ok(answer(
+            format!("The least common multiple of the numbers {:?} is", numbers),
+            l.to_string(),
+        )),
+    }
+}
+
+
+// --- Extended Euclid ---
+
+fn get_egcd_form(request: &mut Request) -> IronResult<Response> {
+    Ok(show_form(&pages::EGCD, request))
+}
+
+fn post_egcd(request: &mut Request) -> IronResult<Response> {
+    Ok(show_answer(&pages::EGCD, request, compute_egcd))
+}
+
+fn compute_egcd(request: &mut Request) -> Result<Answer, Failure> {
+    let a = parse_number(request, "a")?;
+    let b = parse_number(request, "b")?;
+
+    let (g, x, y) = extended_gcd(a, b);
+
+    Ok(answer(
+        format!(
+            "{a} × ({x}) + {b} × ({y}) = gcd({a}, {b}) =",
+            a = a,
+            b = b,
+            x = x,
+            y = y
+        ),
+        g.to_string(),
+    ))
+}
+
+
+// --- Modular inverse ---
+
+fn get_modinv_form(request: &mut Request) -> IronResult<Response> {
+    Ok(show_form(&pages::MODINV, request))
+}
+
+fn post_modinv(request: &mut Request) -> IronResult<Response> {
+    Ok(show_answer(&pages::MODINV, request, compute_modinv))
+}
+
+fn compute_modinv(request: &mut Request) -> Result<Answer, Failure> {
+    let a = parse_number(request, "a")?;
+    let m = parse_number(request, "m")?;
+
+    match mod_inverse(a, m) {
+        None => Err(unprocessable(format!("{} has no inverse modulo {}\n", a, m))),
+        Some(inverse) => Ok(answer(
+            format!("The inverse of {} modulo {} is", a, m),
+            inverse.to_string(),
+        )),
+    }
+}
+
+
+// --- Prime factorization ---
+
+fn get_factor_form(request: &mut Request) -> IronResult<Response> {
+    Ok(show_form(&pages::FACTOR, request))
+}
+
+fn post_factor(request: &mut Request) -> IronResult<Response> {
+    Ok(show_answer(&pages::FACTOR, request, compute_factor))
+}
+
+fn compute_factor(request: &mut Request) -> Result<Answer, Failure> {
+    let n = parse_number(request, "n")?;
+
+    if n == 0 {
+        return Err(unprocessable(format!("0 has no prime factorization\n")));
+    }
+
+    let factors: Vec<String> = factorize(n).iter().map(|p| p.to_string()).collect();
+    let product = if factors.is_empty() {
+        String::from("1")
+    } else {
+        factors.join(" × ")
+    };
+
+    Ok(answer(format!("{} =", n), product))
+}
+
+
+// --- Batch CSV ---
+
+fn get_batch_form(_request: &mut Request) -> IronResult<Response> {
+    let page = Batch { nav: pages::nav("/batch") };
+    Ok(html(page.render().unwrap()))
+}
+
+fn post_batch(request: &mut Request) -> IronResult<Response> {
+    let body = match batch::read_upload(request) {
+        Err(UploadError::TooLarge(max_bytes)) => {
+            return Ok(text_error(too_large(format!("{}\n", limits::body_too_large(max_bytes)))))
+        }
+        Err(UploadError::Io(e)) => return Ok(text_error(bad_request(format!("Could not read the upload: {}\n", e)))),
+        Ok(None) => return Ok(text_error(bad_request(format!("form data has no 'file' field\n")))),
+        Ok(Some(body)) => body,
+    };
+
+    let mut response = Response::new();
+    response.set_mut(status::Ok);
+    response.set_mut("text/csv; charset=utf-8".parse::<Mime>().unwrap());
+    response
+        .headers
+        .set_raw("Content-Disposition", vec![b"attachment; filename=\"gcd.csv\"".to_vec()]);
+    // no Content-Length, hyper sends it chunked as the rows are computed
+    response.body = Some(Box::new(body));
     Ok(response)
 }
 
+#[cfg(test)]
+fn post_form(body: &str) -> Response {
+    iron_test::request::post("http://localhost:3000/gcd", iron::Headers::new(), body, &post_gcd)
+        .unwrap()
+}
+
+// percent encode every byte so any string can be sent as a form value
+#[cfg(test)]
+fn encode(value: &str) -> String {
+    value.bytes().map(|b| format!("%{:02X}", b)).collect()
+}
+
+#[test]
+fn test_post_gcd_with_zero() {
+  let response = post_form("n=0&n=12");
+  assert_eq!(response.status, Some(status::Ok));
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(body.contains("<b>12</b>"));
+}
+
+#[test]
+fn test_post_gcd_single_value() {
+  let response = post_form("n=42");
+  assert_eq!(response.status, Some(status::Ok));
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(body.contains("<b>42</b>"));
+}
+
+#[test]
+fn test_post_gcd_invalid_values() {
+  assert_eq!(post_form("").status, Some(status::BadRequest));
+  assert_eq!(post_form("n=").status, Some(status::BadRequest));
+  assert_eq!(post_form("m=12").status, Some(status::BadRequest));
+  assert_eq!(post_form("n=twelve").status, Some(status::BadRequest));
+  assert_eq!(post_form("n=-1").status, Some(status::BadRequest));
+}
+
+#[test]
+fn test_post_gcd_trace() {
+  let response = post_form("n=24&n=81&trace=on");
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(body.contains("<caption>gcd(24, 81) = 3</caption>"));
+  assert!(body.contains("<tr><td>2</td><td>yes</td><td>9</td><td>24</td><td>6</td></tr>"));
+  assert!(body.contains(r#"name="trace" checked"#));
+
+  let response = post_form("n=24&n=81");
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(!body.contains("<table"));
+}
+
+#[test]
+fn test_pages_keep_input_and_history() {
+  let response = post_form("n=12&n=18&n=30");
+  let set_cookie = response.headers.get::<iron::headers::SetCookie>().unwrap().0[0].clone();
+  let body = iron_test::response::extract_body_to_string(response);
+  // one row per submitted number, not only the default two
+  assert_eq!(body.matches(r#"class="remove""#).count(), 3);
+  assert!(body.contains(r#"value="30""#));
+  assert!(body.contains("gcd(12, 18, 30) = 6"));
+
+  // the history comes back with the cookie and errors do not touch it
+  let mut headers = iron::Headers::new();
+  headers.set(iron::headers::Cookie(vec![set_cookie.split(';').next().unwrap().to_string()]));
+  let response = iron_test::request::post("http://localhost:3000/lcm", headers, "n=0&n=x", &post_lcm)
+      .unwrap();
+  assert_eq!(response.status, Some(status::BadRequest));
+  assert!(response.headers.get::<iron::headers::SetCookie>().is_none());
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(body.contains("gcd(12, 18, 30) = 6"));
+  assert!(body.contains(r#"<p class="error">Value for &#x27;n&#x27; is not a number"#));
+}
 
 #[test]
-fn test_gcd() {
-  assert_eq!(gcd(14, 15), 1);
-  assert_eq!(gcd(50, 50), 50);
-  assert_eq!(gcd(24, 81), 3);
+fn test_post_batch() {
+  let post = |headers, body: &str| {
+      let response = iron_test::request::post("http://localhost:3000/batch", headers, body, &post_batch)
+          .unwrap();
+      (response.status, iron_test::response::extract_body_to_string(response))
+  };
+
+  let (status, body) = post(iron::Headers::new(), "12,18\n4,x\n24,81\n");
+  assert_eq!(status, Some(status::Ok));
+  assert_eq!(body, "12,18,6,36\r\nERROR,2,\"value \"\"x\"\" is not a number\"\r\n24,81,3,648\r\n");
+
+  let mut headers = iron::Headers::new();
+  headers.set_raw("Content-Type", vec![b"multipart/form-data; boundary=XyZ".to_vec()]);
+  let form = "--XyZ\r\n\
+              Content-Disposition: form-data; name=\"file\"; filename=\"n.csv\"\r\n\
+              Content-Type: text/csv\r\n\r\n\
+              14,15\r\n50,50\r\n\
+              --XyZ--\r\n";
+  let (status, body) = post(headers.clone(), form);
+  assert_eq!(status, Some(status::Ok));
+  assert_eq!(body, "14,15,1,210\r\n50,50,50,50\r\n");
+
+  let form = "--XyZ\r\nContent-Disposition: form-data; name=\"other\"\r\n\r\n1\r\n--XyZ--\r\n";
+  let (status, _) = post(headers, form);
+  assert_eq!(status, Some(status::BadRequest));
+}
+
+#[test]
+fn test_post_api_gcd() {
+  let post = |body: &str| {
+      iron_test::request::post("http://localhost:3000/api/gcd", iron::Headers::new(), body, &post_api_gcd)
+          .unwrap()
+  };
+
+  let response = post("n=24&n=81");
+  assert_eq!(response.status, Some(status::Ok));
+  let body: serde_json::Value =
+      serde_json::from_str(&iron_test::response::extract_body_to_string(response)).unwrap();
+  assert_eq!(body["gcd"], 3);
+  assert!(body.get("trace").is_none());
+
+  let response = post("n=24&n=81&trace=on");
+  let body: serde_json::Value =
+      serde_json::from_str(&iron_test::response::extract_body_to_string(response)).unwrap();
+  assert_eq!(body["trace"][0]["steps"][1]["remainder"], 6);
+  assert_eq!(body["trace"][0]["steps"][1]["swapped"], true);
+
+  let response = post("n=abc");
+  assert_eq!(response.status, Some(status::BadRequest));
+  let body: serde_json::Value =
+      serde_json::from_str(&iron_test::response::extract_body_to_string(response)).unwrap();
+  assert_eq!(body["error"], "Value for 'n' is not a number: \"abc\"");
+
+  let response = post("n=36893488147419103232&n=24&big=on");
+  assert_eq!(response.status, Some(status::Ok));
+  let body: serde_json::Value =
+      serde_json::from_str(&iron_test::response::extract_body_to_string(response)).unwrap();
+  assert_eq!(body["gcd"], "8");
+  assert_eq!(body["numbers"][0], "36893488147419103232");
+
+  let response = post("n=24&n=81&big=on&trace=on");
+  assert_eq!(response.status, Some(status::UnprocessableEntity));
+}
+
+#[test]
+fn test_middleware_chain() {
+  let app = app(&Limits::default());
+
+  let response = iron_test::request::post("http://localhost:3000/gcd", iron::Headers::new(), "n=4&n=6", &app)
+      .unwrap();
+  assert!(response.headers.get_raw("X-Request-Id").is_some());
+
+  let mut headers = iron::Headers::new();
+  headers.set_raw("X-Request-Id", vec![b"abc-123".to_vec()]);
+  let response = iron_test::request::post("http://localhost:3000/gcd", headers, "n=x", &app).unwrap();
+  assert_eq!(response.headers.get_raw("X-Request-Id").unwrap()[0], b"abc-123".to_vec());
+
+  // unknown routes are an Err for the chain, but still counted
+  assert!(iron_test::request::get("http://localhost:3000/nope", iron::Headers::new(), &app).is_err());
+
+  let response = iron_test::request::get("http://localhost:3000/metrics", iron::Headers::new(), &app).unwrap();
+  let body = iron_test::response::extract_body_to_string(response);
+  assert!(body.contains("gcd_requests_total{route=\"/gcd\",method=\"POST\",status=\"200\"} 1\n"));
+  assert!(body.contains("gcd_requests_total{route=\"/gcd\",method=\"POST\",status=\"400\"} 1\n"));
+  assert!(body.contains("gcd_requests_total{route=\"unmatched\",method=\"GET\",status=\"404\"} 1\n"));
+  assert!(body.contains("gcd_errors_total{route=\"/gcd\"} 1\n"));
+  assert!(body.contains("gcd_request_duration_seconds_count{route=\"/gcd\"} 2\n"));
+}
+
+#[cfg(test)]
+quickcheck! {
+    fn prop_post_gcd_never_panics(body: String) -> bool {
+        post_form(&body).status.is_some()
+    }
+
+    fn prop_post_gcd_never_panics_on_any_value(values: Vec<String>, big: bool) -> bool {
+        let mut fields: Vec<String> = values.iter().map(|v| format!("n={}", encode(v))).collect();
+        if big {
+            fields.push(String::from("big=on"));
+        }
+        post_form(&fields.join("&")).status.is_some()
+    }
+
+    fn prop_post_gcd_accepts_any_u64(numbers: Vec<u64>) -> bool {
+        if numbers.is_empty() {
+            return true;
+        }
+        let fields: Vec<String> = numbers.iter().map(|n| format!("n={}", n)).collect();
+        let response = post_form(&fields.join("&"));
+        let expected = format!("<b>{}</b>", gcd_list(&numbers));
+        response.status == Some(status::Ok)
+            && iron_test::response::extract_body_to_string(response).contains(&expected)
+    }
 }
@@ -0,0 +1,344 @@
+// Middleware wrapped around the router
+//
+//   RequestId, RateLimit, BodyLimit (before) -> router -> Metrics, AccessLog (after)
+//
+// RateLimit and BodyLimit are in limits.rs.
+// RequestId stores the request id and the start time in the request
+// extensions so the after middlewares can use them.
+
+use iron::mime::Mime;
+use iron::prelude::*;
+use iron::status;
+use iron::typemap::Key;
+use iron::{AfterMiddleware, BeforeMiddleware, Handler};
+use serde_json;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+pub struct RequestStart {
+    pub id: String,
+    pub instant: Instant,
+}
+
+impl Key for RequestStart {
+    type Value = RequestStart;
+}
+
+pub fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+pub fn path(request: &Request) -> String {
+    format!("/{}", request.url.path().join("/"))
+}
+
+fn elapsed(request: &Request) -> Duration {
+    match request.extensions.get::<RequestStart>() {
+        Some(start) => start.instant.elapsed(),
+        None => Duration::from_secs(0),
+    }
+}
+
+
+// --- Request id ---
+
+// Ids look like `5b2f9c1a-42`: the server start time and a counter,
+// unique enough to grep the logs of a single service.
+// An id sent by a proxy in `X-Request-Id` is kept if it looks sane.
+pub struct RequestId {
+    prefix: String,
+    counter: AtomicUsize,
+}
+
+impl RequestId {
+    pub fn new() -> RequestId {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        RequestId {
+            prefix: format!("{:x}", started),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    fn from_header(request: &Request) -> Option<String> {
+        let raw = request.headers.get_raw(REQUEST_ID_HEADER)?;
+        let id = String::from_utf8(raw.first()?.clone()).ok()?;
+        let valid = !id.is_empty() && id.len() <= 64
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Some(id)
+        } else {
+            None
+        }
+    }
+}
+
+impl BeforeMiddleware for RequestId {
+    fn before(&self, request: &mut Request) -> IronResult<()> {
+        let id = match RequestId::from_header(request) {
+            Some(id) => id,
+            None => {
+                let n = self.counter.fetch_add(1, Ordering::SeqCst);
+                format!("{}-{}", self.prefix, n)
+            }
+        };
+        request.extensions.insert::<RequestStart>(RequestStart {
+            id,
+            instant: Instant::now(),
+        });
+        Ok(())
+    }
+}
+
+
+// --- Access log ---
+
+// One JSON object per line, easy to ship to any log collector
+#[derive(Serialize)]
+struct AccessLogLine<'a> {
+    request_id: &'a str,
+    remote_addr: String,
+    method: String,
+    path: String,
+    status: u16,
+    duration_ms: f64,
+}
+
+pub struct AccessLog;
+
+impl AccessLog {
+    fn log(&self, request: &Request, response: &mut Response) {
+        let id = match request.extensions.get::<RequestStart>() {
+            Some(start) => start.id.clone(),
+            None => String::from("-"),
+        };
+        let line = AccessLogLine {
+            request_id: &id,
+            remote_addr: request.remote_addr.to_string(),
+            method: request.method.to_string(),
+            path: path(request),
+            status: response.status.unwrap_or(status::NotFound).to_u16(),
+            duration_ms: seconds(elapsed(request)) * 1000.0,
+        };
+        println!("{}", serde_json::to_string(&line).unwrap());
+
+        response.headers.set_raw(REQUEST_ID_HEADER, vec![id.into_bytes()]);
+    }
+}
+
+impl AfterMiddleware for AccessLog {
+    fn after(&self, request: &mut Request, mut response: Response) -> IronResult<Response> {
+        self.log(request, &mut response);
+        Ok(response)
+    }
+
+    fn catch(&self, request: &mut Request, mut err: IronError) -> IronResult<Response> {
+        self.log(request, &mut err.response);
+        Err(err)
+    }
+}
+
+
+// --- Prometheus metrics ---
+
+// upper bounds in seconds, the `+Inf` bucket is implicit
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    // not cumulative, `render` sums them up
+    buckets: [u64; 11],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(i) = BUCKETS.iter().position(|&bound| value <= bound) {
+            self.buckets[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    // (route, method, status) -> count
+    requests: BTreeMap<(String, String, u16), u64>,
+    // route -> count
+    errors: BTreeMap<String, u64>,
+    // route -> latency
+    latency: BTreeMap<String, Histogram>,
+}
+
+impl Registry {
+    pub fn record(&mut self, route: &str, method: &str, status: u16, duration: Duration) {
+        *self.requests
+            .entry((route.to_string(), method.to_string(), status))
+            .or_insert(0) += 1;
+        if status >= 400 {
+            *self.errors.entry(route.to_string()).or_insert(0) += 1;
+        }
+        self.latency
+            .entry(route.to_string())
+            .or_insert_with(Histogram::default)
+            .observe(seconds(duration));
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP gcd_requests_total Number of HTTP requests by route, method and status.\n");
+        out.push_str("# TYPE gcd_requests_total counter\n");
+        for (&(ref route, ref method, status), count) in &self.requests {
+            out.push_str(&format!(
+                "gcd_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}\n",
+                route, method, status, count
+            ));
+        }
+
+        out.push_str("# HELP gcd_errors_total Number of HTTP requests answered with a 4xx or 5xx status.\n");
+        out.push_str("# TYPE gcd_errors_total counter\n");
+        for (route, count) in &self.errors {
+            out.push_str(&format!("gcd_errors_total{{route=\"{}\"}} {}\n", route, count));
+        }
+
+        out.push_str("# HELP gcd_request_duration_seconds Time spent answering HTTP requests.\n");
+        out.push_str("# TYPE gcd_request_duration_seconds histogram\n");
+        for (route, histogram) in &self.latency {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                out.push_str(&format!(
+                    "gcd_request_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}\n",
+                    route, bound, cumulative
+                ));
+            }
+            out.push_str(&format!(
+                "gcd_request_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}\n",
+                route, histogram.count
+            ));
+            out.push_str(&format!(
+                "gcd_request_duration_seconds_sum{{route=\"{}\"}} {}\n",
+                route, histogram.sum
+            ));
+            out.push_str(&format!(
+                "gcd_request_duration_seconds_count{{route=\"{}\"}} {}\n",
+                route, histogram.count
+            ));
+        }
+
+        out
+    }
+}
+
+// Cheap to clone: the middleware and the /metrics handler share the registry
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+    // the paths of the router, every other path is "unmatched"
+    routes: Arc<Vec<&'static str>>,
+}
+
+impl Metrics {
+    pub fn new(routes: &[&'static str]) -> Metrics {
+        Metrics {
+            registry: Arc::new(Mutex::new(Registry::default())),
+            routes: Arc::new(routes.to_vec()),
+        }
+    }
+
+    pub fn handler(&self) -> MetricsHandler {
+        MetricsHandler { metrics: self.clone() }
+    }
+
+    fn record(&self, request: &Request, response: &Response) {
+        let status = response.status.unwrap_or(status::NotFound).to_u16();
+        // every unknown path would become a new time series, whatever
+        // the status (404, or 429 and 413 from limits.rs), group them
+        let path = path(request);
+        let route = match self.routes.iter().find(|&&route| route == path) {
+            Some(&route) => route,
+            None => "unmatched",
+        };
+        self.registry.lock().unwrap().record(
+            route,
+            &request.method.to_string(),
+            status,
+            elapsed(request),
+        );
+    }
+}
+
+impl AfterMiddleware for Metrics {
+    fn after(&self, request: &mut Request, response: Response) -> IronResult<Response> {
+        self.record(request, &response);
+        Ok(response)
+    }
+
+    fn catch(&self, request: &mut Request, err: IronError) -> IronResult<Response> {
+        self.record(request, &err.response);
+        Err(err)
+    }
+}
+
+pub struct MetricsHandler {
+    metrics: Metrics,
+}
+
+impl Handler for MetricsHandler {
+    fn handle(&self, _request: &mut Request) -> IronResult<Response> {
+        let body = self.metrics.registry.lock().unwrap().render();
+        let mut response = Response::new();
+        response.set_mut(status::Ok);
+        response.set_mut("text/plain; version=0.0.4; charset=utf-8".parse::<Mime>().unwrap());
+        response.set_mut(body);
+        Ok(response)
+    }
+}
+
+
+#[test]
+fn test_registry_render() {
+  let mut registry = Registry::default();
+  registry.record("/gcd", "POST", 200, Duration::from_millis(3));
+  registry.record("/gcd", "POST", 200, Duration::from_millis(30));
+  registry.record("/gcd", "POST", 400, Duration::from_millis(3));
+
+  let text = registry.render();
+  assert!(text.contains("gcd_requests_total{route=\"/gcd\",method=\"POST\",status=\"200\"} 2\n"));
+  assert!(text.contains("gcd_requests_total{route=\"/gcd\",method=\"POST\",status=\"400\"} 1\n"));
+  assert!(text.contains("gcd_errors_total{route=\"/gcd\"} 1\n"));
+  assert!(text.contains("gcd_request_duration_seconds_bucket{route=\"/gcd\",le=\"0.005\"} 2\n"));
+  assert!(text.contains("gcd_request_duration_seconds_bucket{route=\"/gcd\",le=\"0.05\"} 3\n"));
+  assert!(text.contains("gcd_request_duration_seconds_bucket{route=\"/gcd\",le=\"+Inf\"} 3\n"));
+  assert!(text.contains("gcd_request_duration_seconds_count{route=\"/gcd\"} 3\n"));
+}
+
+#[test]
+fn test_unknown_paths_are_unmatched() {
+  use iron_test::request;
+
+  // like the 429 and 413 of limits.rs, answered before the router
+  let refuse = |_: &mut Request| -> IronResult<Response> { Ok(Response::with(status::TooManyRequests)) };
+  let metrics = Metrics::new(&["/gcd"]);
+  let mut chain = Chain::new(refuse);
+  chain.link_after(metrics.clone());
+  for path in &["/gcd", "/random-1", "/random-2"] {
+      request::post(&format!("http://localhost:3000{}", path), iron::Headers::new(), "", &chain).unwrap();
+  }
+
+  let text = metrics.registry.lock().unwrap().render();
+  assert!(text.contains("gcd_requests_total{route=\"/gcd\",method=\"POST\",status=\"429\"} 1\n"));
+  assert!(text.contains("gcd_requests_total{route=\"unmatched\",method=\"POST\",status=\"429\"} 2\n"));
+  assert!(!text.contains("random"));
+}
@@ -0,0 +1,372 @@
+// Number theory helpers used by the web handlers.
+// Everything here started as the single `gcd` function in main.rs.
+
+
+use std::mem;
+
+
+// One iteration of Euclid's algorithm:
+// `n` and `m` are the values after the swap and `remainder` is m % n
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Step {
+    pub n: u64,
+    pub m: u64,
+    pub swapped: bool,
+    pub remainder: u64,
+}
+
+// Iterator over the steps of gcd(n, m), `gcd` just drives it to the end
+pub struct EuclidSteps {
+    n: u64,
+    m: u64,
+}
+
+pub fn gcd_steps(n: u64, m: u64) -> EuclidSteps {
+    EuclidSteps { n, m }
+}
+
+impl EuclidSteps {
+    // Every number divides 0, so gcd(0, m) = m and gcd(0, 0) = 0
+    // only meaningful once the iterator is exhausted
+    pub fn result(&self) -> u64 {
+        if self.n == 0 {
+            self.m
+        } else {
+            self.n
+        }
+    }
+}
+
+impl Iterator for EuclidSteps {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if self.n == 0 || self.m == 0 {
+            return None;
+        }
+        let swapped = self.m < self.n;
+        if swapped {
+            mem::swap(&mut self.n, &mut self.m);
+        }
+        let (n, m) = (self.n, self.m);
+        self.m = m % n;
+        Some(Step { n, m, swapped, remainder: self.m })
+    }
+}
+
+pub fn gcd(n: u64, m: u64) -> u64 {
+    let mut steps = gcd_steps(n, m);
+    steps.by_ref().count();
+    steps.result()
+}
+
+// 0 is the identity of gcd, so the gcd of a single number is itself
+// and the gcd of an empty list is 0
+pub fn gcd_list(numbers: &[u64]) -> u64 {
+    numbers.iter().fold(0, |d, &m| gcd(d, m))
+}
+
+// The gcd of a list is computed pairwise: gcd(gcd(a, b), c)...
+// each Reduction records one of those pairs and its steps
+#[derive(Debug, Serialize)]
+pub struct Reduction {
+    pub n: u64,
+    pub m: u64,
+    pub steps: Vec<Step>,
+    pub gcd: u64,
+}
+
+pub fn gcd_list_steps(numbers: &[u64]) -> Vec<Reduction> {
+    let mut reductions = Vec::new();
+    if numbers.is_empty() {
+        return reductions;
+    }
+
+    let mut d = numbers[0];
+    for &m in &numbers[1..] {
+        let mut steps = gcd_steps(d, m);
+        let trace: Vec<Step> = steps.by_ref().collect();
+        let result = steps.result();
+        reductions.push(Reduction { n: d, m, steps: trace, gcd: result });
+        d = result;
+    }
+    reductions
+}
+
+// lcm(n, m) = n / gcd(n, m) * m
+// dividing first keeps the intermediate value small, but the result
+// itself may still not fit in a u64, so we return None on overflow
+pub fn lcm(n: u64, m: u64) -> Option<u64> {
+    if n == 0 || m == 0 {
+        return Some(0);
+    }
+    (n / gcd(n, m)).checked_mul(m)
+}
+
+// 1 is the identity of lcm
+pub fn lcm_list(numbers: &[u64]) -> Option<u64> {
+    let mut l = 1;
+    for m in numbers {
+        l = lcm(l, *m)?;
+    }
+    Some(l)
+}
+
+// Extended Euclidean algorithm
+// returns (g, x, y) such that a*x + b*y = g = gcd(a, b)
+// the Bézout coefficients can be negative and can be as large as the
+// inputs, so they are returned as i128 to never overflow.
+pub fn extended_gcd(a: u64, b: u64) -> (u64, i128, i128) {
+    let (mut old_r, mut r) = (a as i128, b as i128);
+    let (mut old_x, mut x) = (1i128, 0i128);
+    let (mut old_y, mut y) = (0i128, 1i128);
+
+    while r != 0 {
+        let q = old_r / r;
+
+        let t = r;
+        r = old_r - q * r;
+        old_r = t;
+
+        let t = x;
+        x = old_x - q * x;
+        old_x = t;
+
+        let t = y;
+        y = old_y - q * y;
+        old_y = t;
+    }
+
+    (old_r as u64, old_x, old_y)
+}
+
+// The inverse of `a` modulo `m` is the `x` in a*x ≡ 1 (mod m)
+// it only exists when gcd(a, m) == 1
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a % m, m);
+    if g != 1 {
+        return None;
+    }
+    let m = m as i128;
+    Some((((x % m) + m) % m) as u64)
+}
+
+
+// --- Prime factorization ---
+
+// a * b % m without overflowing, u128 holds any product of two u64
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base = base % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+// Testing against the first 12 primes as witnesses makes
+// Miller-Rabin deterministic for every n < 2^64
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in WITNESSES.iter() {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+
+    // write n - 1 as d * 2^s with d odd
+    let mut d = n - 1;
+    let mut s = 0;
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+
+    'witness: for &a in WITNESSES.iter() {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Pollard's rho: finds a non trivial divisor of a composite `n`
+// x is the tortoise and y the hare walking over f(x) = x² + c (mod n)
+// when they meet without finding a divisor we try again with another `c`
+fn pollard_rho(n: u64) -> u64 {
+    if n % 2 == 0 {
+        return 2;
+    }
+    let mut c = 1;
+    loop {
+        let f = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            let diff = if x > y { x - y } else { y - x };
+            d = gcd(diff, n);
+        }
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+fn collect_factors(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        factors.push(n);
+        return;
+    }
+    let d = pollard_rho(n);
+    collect_factors(d, factors);
+    collect_factors(n / d, factors);
+}
+
+// Returns the prime factors of `n` in ascending order, with repetition
+// factorize(12) == [2, 2, 3]
+pub fn factorize(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return factors;
+    }
+
+    // trial division is faster than rho for the small factors
+    for p in 2..1000 {
+        while n % p == 0 {
+            factors.push(p);
+            n /= p;
+        }
+    }
+
+    collect_factors(n, &mut factors);
+    factors.sort();
+    factors
+}
+
+
+#[test]
+fn test_gcd() {
+  assert_eq!(gcd(14, 15), 1);
+  assert_eq!(gcd(50, 50), 50);
+  assert_eq!(gcd(24, 81), 3);
+}
+
+#[test]
+fn test_gcd_with_zero() {
+  assert_eq!(gcd(0, 15), 15);
+  assert_eq!(gcd(15, 0), 15);
+  assert_eq!(gcd(0, 0), 0);
+  assert_eq!(gcd_list(&[42]), 42);
+  assert_eq!(gcd_list(&[0, 42]), 42);
+  assert_eq!(gcd_list(&[]), 0);
+}
+
+#[test]
+fn test_gcd_steps() {
+  let steps: Vec<Step> = gcd_steps(24, 81).collect();
+  assert_eq!(
+      steps,
+      vec![
+          Step { n: 24, m: 81, swapped: false, remainder: 9 },
+          Step { n: 9, m: 24, swapped: true, remainder: 6 },
+          Step { n: 6, m: 9, swapped: true, remainder: 3 },
+          Step { n: 3, m: 6, swapped: true, remainder: 0 },
+      ]
+  );
+  assert_eq!(gcd_steps(0, 15).count(), 0);
+
+  let reductions = gcd_list_steps(&[24, 81, 5]);
+  assert_eq!(reductions.len(), 2);
+  assert_eq!((reductions[0].n, reductions[0].m, reductions[0].gcd), (24, 81, 3));
+  assert_eq!((reductions[1].n, reductions[1].m, reductions[1].gcd), (3, 5, 1));
+}
+
+#[test]
+fn test_lcm() {
+  assert_eq!(lcm(4, 6), Some(12));
+  assert_eq!(lcm(21, 6), Some(42));
+  assert_eq!(lcm_list(&[2, 3, 4, 5]), Some(60));
+  assert_eq!(lcm(u64::max_value(), u64::max_value() - 1), None);
+  assert_eq!(lcm(0, 6), Some(0));
+  assert_eq!(lcm_list(&[7]), Some(7));
+}
+
+#[test]
+fn test_extended_gcd() {
+  assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+  let (g, x, y) = extended_gcd(24, 81);
+  assert_eq!(g, 3);
+  assert_eq!(24 * x + 81 * y, 3);
+}
+
+#[test]
+fn test_mod_inverse() {
+  assert_eq!(mod_inverse(3, 11), Some(4));
+  assert_eq!(mod_inverse(10, 17), Some(12));
+  assert_eq!(mod_inverse(6, 9), None);
+  assert_eq!(mod_inverse(3, 0), None);
+}
+
+#[test]
+fn test_factorize() {
+  assert_eq!(factorize(1), Vec::<u64>::new());
+  assert_eq!(factorize(12), vec![2, 2, 3]);
+  assert_eq!(factorize(97), vec![97]);
+  // two primes larger than the trial division limit, found by rho
+  assert_eq!(factorize(1000003 * 1000033), vec![1000003, 1000033]);
+  assert_eq!(factorize(18446744073709551557), vec![18446744073709551557]);
+  assert_eq!(factorize(u64::max_value()), vec![3, 5, 17, 257, 641, 65537, 6700417]);
+}
+
+#[cfg(test)]
+quickcheck! {
+    fn prop_gcd_divides_both(n: u64, m: u64) -> bool {
+        let d = gcd(n, m);
+        if d == 0 {
+            n == 0 && m == 0
+        } else {
+            n % d == 0 && m % d == 0
+        }
+    }
+
+    fn prop_gcd_is_commutative(n: u64, m: u64) -> bool {
+        gcd(n, m) == gcd(m, n)
+    }
+
+    fn prop_gcd_list_of_one_is_itself(n: u64) -> bool {
+        gcd_list(&[n]) == n
+    }
+
+    fn prop_bezout_identity(a: u64, b: u64) -> bool {
+        let (g, x, y) = extended_gcd(a, b);
+        g == gcd(a, b) && a as i128 * x + b as i128 * y == g as i128
+    }
+}
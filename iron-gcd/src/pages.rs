@@ -0,0 +1,158 @@
+// HTML pages rendered with askama, see the templates folder
+//
+// All the calculators share `calculator.html`, an `Operation`
+// describes what changes from one calculator to the other.
+
+use askama::Template;
+use number_theory::Reduction;
+use session::Calculation;
+
+
+pub struct Operation {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub action: &'static str,
+    pub button: &'static str,
+    // a name listed more than once is repeatable: rows can be added and removed
+    pub fields: &'static [&'static str],
+    // (name, label)
+    pub checkboxes: &'static [(&'static str, &'static str)],
+}
+
+pub const GCD: Operation = Operation {
+    name: "gcd",
+    title: "GCD Calculator",
+    action: "/gcd",
+    button: "Compute GCD",
+    fields: &["n", "n"],
+    checkboxes: &[
+        ("big", "Big integers (more than 20 digits)"),
+        ("trace", "Show the steps of Euclid's algorithm"),
+    ],
+};
+
+pub const LCM: Operation = Operation {
+    name: "lcm",
+    title: "LCM Calculator",
+    action: "/lcm",
+    button: "Compute LCM",
+    fields: &["n", "n"],
+    checkboxes: &[],
+};
+
+pub const EGCD: Operation = Operation {
+    name: "egcd",
+    title: "Extended Euclidean Algorithm",
+    action: "/egcd",
+    button: "Compute Bézout coefficients",
+    fields: &["a", "b"],
+    checkboxes: &[],
+};
+
+pub const MODINV: Operation = Operation {
+    name: "modinv",
+    title: "Modular Inverse",
+    action: "/modinv",
+    button: "Compute inverse",
+    fields: &["a", "m"],
+    checkboxes: &[],
+};
+
+pub const FACTOR: Operation = Operation {
+    name: "factor",
+    title: "Prime Factorization",
+    action: "/factor",
+    button: "Factorize",
+    fields: &["n"],
+    checkboxes: &[],
+};
+
+pub const OPERATIONS: [&Operation; 5] = [&GCD, &LCM, &EGCD, &MODINV, &FACTOR];
+
+impl Operation {
+    pub fn is_repeatable(&self, name: &str) -> bool {
+        self.fields.iter().filter(|field| **field == name).count() > 1
+    }
+}
+
+
+pub struct Field {
+    pub name: &'static str,
+    pub value: String,
+    pub repeatable: bool,
+}
+
+pub struct Checkbox {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub checked: bool,
+}
+
+// Rendered as `{{ text }} <b>{{ value }}</b>`
+pub struct Answer {
+    pub text: String,
+    pub value: String,
+    // Euclid's steps, only when the trace was asked for
+    pub reductions: Vec<Reduction>,
+}
+
+pub struct Link {
+    pub href: &'static str,
+    pub title: &'static str,
+    pub active: bool,
+}
+
+// One link per calculator plus the batch upload, `active` is the current href
+pub fn nav(active: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = OPERATIONS
+        .iter()
+        .map(|operation| {
+            let href = if operation.name == GCD.name { "/" } else { operation.action };
+            Link { href, title: operation.title, active: href == active }
+        })
+        .collect();
+    links.push(Link { href: "/batch", title: "Batch CSV", active: active == "/batch" });
+    links
+}
+
+#[derive(Template)]
+#[template(path = "calculator.html")]
+pub struct Calculator<'a> {
+    pub nav: Vec<Link>,
+    pub title: &'static str,
+    pub action: &'static str,
+    pub button: &'static str,
+    pub repeatable: bool,
+    pub fields: Vec<Field>,
+    pub checkboxes: Vec<Checkbox>,
+    pub answer: Option<Answer>,
+    pub error: Option<String>,
+    pub history: &'a [Calculation],
+}
+
+impl<'a> Calculator<'a> {
+    pub fn new(operation: &Operation, fields: Vec<Field>, history: &'a [Calculation]) -> Calculator<'a> {
+        Calculator {
+            nav: nav(if operation.name == GCD.name { "/" } else { operation.action }),
+            title: operation.title,
+            action: operation.action,
+            button: operation.button,
+            repeatable: fields.iter().any(|field| field.repeatable),
+            fields,
+            checkboxes: operation
+                .checkboxes
+                .iter()
+                .map(|&(name, label)| Checkbox { name, label, checked: false })
+                .collect(),
+            answer: None,
+            error: None,
+            history,
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "batch.html")]
+pub struct Batch {
+    pub nav: Vec<Link>,
+}
@@ -0,0 +1,136 @@
+// Cookie backed session holding the recent calculations
+//
+// Nothing is stored on the server: the history itself is the cookie value,
+// JSON encoded as hex so it only uses characters allowed in a cookie.
+// A tampered cookie only changes what the same user sees, and every
+// value is escaped by the templates, so it is not signed.
+
+use iron::headers::{Cookie, SetCookie};
+use iron::prelude::*;
+use serde_json;
+
+
+const COOKIE_NAME: &str = "gcd_history";
+const MAX_ENTRIES: usize = 10;
+// browsers limit a cookie to 4KB, big integers would not fit
+const MAX_VALUE_LEN: usize = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calculation {
+    pub operation: String,
+    pub input: String,
+    pub result: String,
+}
+
+fn shorten(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_LEN {
+        value.to_string()
+    } else {
+        let start: String = value.chars().take(MAX_VALUE_LEN).collect();
+        format!("{}…", start)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Newest calculation first
+#[derive(Debug, Default, PartialEq)]
+pub struct History {
+    pub entries: Vec<Calculation>,
+}
+
+impl History {
+    // A missing or broken cookie is just an empty history
+    pub fn load(request: &Request) -> History {
+        let cookies = match request.headers.get::<Cookie>() {
+            Some(&Cookie(ref cookies)) => cookies.clone(),
+            None => return History::default(),
+        };
+
+        for cookie in cookies {
+            let mut parts = cookie.splitn(2, '=');
+            if parts.next().map(|name| name.trim()) != Some(COOKIE_NAME) {
+                continue;
+            }
+            let entries = parts
+                .next()
+                .and_then(|value| from_hex(value.trim()))
+                .and_then(|json| serde_json::from_slice(&json).ok());
+            if let Some(entries) = entries {
+                return History { entries };
+            }
+        }
+        History::default()
+    }
+
+    pub fn push(&mut self, operation: &str, input: &str, result: &str) {
+        self.entries.insert(0, Calculation {
+            operation: operation.to_string(),
+            input: shorten(input),
+            result: shorten(result),
+        });
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn save(&self, response: &mut Response) {
+        let json = serde_json::to_vec(&self.entries).unwrap();
+        response.headers.set(SetCookie(vec![format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax",
+            COOKIE_NAME,
+            to_hex(&json)
+        )]));
+    }
+}
+
+
+#[test]
+fn test_history_is_bounded() {
+  let mut history = History::default();
+  for i in 0..15 {
+      history.push("gcd", &i.to_string(), "1");
+  }
+  assert_eq!(history.entries.len(), MAX_ENTRIES);
+  assert_eq!(history.entries[0].input, "14");
+
+  history.push("gcd", &"9".repeat(100), "1");
+  assert_eq!(history.entries[0].input.chars().count(), MAX_VALUE_LEN + 1);
+}
+
+#[test]
+fn test_history_cookie_roundtrip() {
+  let mut history = History::default();
+  history.push("gcd", "12, 18", "6");
+  history.push("lcm", "4, 6", "12");
+
+  let mut response = Response::new();
+  history.save(&mut response);
+  let set_cookie = response.headers.get::<SetCookie>().unwrap().0[0].clone();
+  let value = set_cookie.split(';').next().unwrap();
+
+  let mut headers = iron::Headers::new();
+  headers.set(Cookie(vec![String::from("other=1"), value.to_string()]));
+  let request = ::iron_test::request::get("http://localhost:3000/", headers, &|request: &mut Request| {
+      Ok(Response::with(format!("{:?}", History::load(request))))
+  }).unwrap();
+  let body = ::iron_test::response::extract_body_to_string(request);
+  assert_eq!(body, format!("{:?}", history));
+}
+
+#[test]
+fn test_broken_cookie_is_ignored() {
+  assert_eq!(from_hex("zz"), None);
+  assert_eq!(from_hex("abc"), None);
+  assert_eq!(from_hex("6869"), Some(b"hi".to_vec()));
+}
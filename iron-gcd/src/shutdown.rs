@@ -0,0 +1,149 @@
+// Graceful shutdown
+//
+// hyper 0.10 can not stop listening (Listening::close does nothing,
+// hyper issue 338), so on SIGINT or SIGTERM the server keeps accepting
+// connections but answers new requests with 503 and `Connection: close`.
+// The requests already in flight go on, main waits for them up to
+// `shutdown_timeout` seconds and then calls process::exit. Returning from
+// main would drop the Listening, which joins the accept thread, and that
+// thread never ends.
+//
+// InFlight wraps the whole app, middleware included, so a request is
+// counted from the first middleware to the last. A streamed body is
+// written after the handler returns, it keeps its own Counted until the
+// last byte (see batch.rs).
+
+use iron::headers::Connection;
+use iron::prelude::*;
+use iron::status;
+use iron::typemap::Key;
+use iron::{AroundMiddleware, Handler};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+struct State {
+    requests: AtomicUsize,
+    draining: AtomicBool,
+}
+
+// Cheap to clone: the middleware and main share the state
+#[derive(Clone)]
+pub struct InFlight {
+    state: Arc<State>,
+}
+
+impl InFlight {
+    pub fn new() -> InFlight {
+        InFlight {
+            state: Arc::new(State {
+                requests: AtomicUsize::new(0),
+                draining: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+
+    // Refuses new requests and waits for the ones in flight,
+    // false when some were still running after `timeout`
+    pub fn drain(&self, timeout: Duration) -> bool {
+        self.state.draining.store(true, Ordering::SeqCst);
+        let start = Instant::now();
+        while self.requests() > 0 {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        true
+    }
+
+    // Counted until the guard is dropped
+    pub fn count(&self) -> Counted {
+        self.state.requests.fetch_add(1, Ordering::SeqCst);
+        Counted(self.state.clone())
+    }
+}
+
+// In the request extensions, for the handlers with a streamed body
+impl Key for InFlight {
+    type Value = InFlight;
+}
+
+// Counts a request while it lives, even when the handler panics
+pub struct Counted(Arc<State>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.requests.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct Tracked {
+    in_flight: InFlight,
+    handler: Box<Handler>,
+}
+
+impl Handler for Tracked {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        // counted before the check, so drain() can not miss a request
+        // that got past it
+        let _counted = self.in_flight.count();
+        if self.in_flight.state.draining.load(Ordering::SeqCst) {
+            let mut response = Response::with((status::ServiceUnavailable, "Shutting down\n"));
+            response.headers.set(Connection::close());
+            return Ok(response);
+        }
+        request.extensions.insert::<InFlight>(self.in_flight.clone());
+        self.handler.handle(request)
+    }
+}
+
+impl AroundMiddleware for InFlight {
+    fn around(self, handler: Box<Handler>) -> Box<Handler> {
+        Box::new(Tracked { in_flight: self, handler })
+    }
+}
+
+
+#[test]
+fn test_drain_waits_for_requests_in_flight() {
+  use iron_test::{request, response};
+
+  let in_flight = InFlight::new();
+  let slow = |_: &mut Request| -> IronResult<Response> {
+      thread::sleep(Duration::from_millis(300));
+      Ok(Response::with((status::Ok, "done")))
+  };
+  let app = Arc::new(in_flight.clone().around(Box::new(slow)));
+
+  let running = {
+      let app = app.clone();
+      thread::spawn(move || request::get("http://localhost:3000/", iron::Headers::new(), &**app).unwrap())
+  };
+  while in_flight.requests() == 0 {
+      thread::sleep(Duration::from_millis(5));
+  }
+
+  assert!(in_flight.drain(Duration::from_secs(5)));
+  let finished = running.join().unwrap();
+  assert_eq!(finished.status, Some(status::Ok));
+  assert_eq!(response::extract_body_to_string(finished), "done");
+
+  // and the requests after the signal are turned away
+  let refused = request::get("http://localhost:3000/", iron::Headers::new(), &**app).unwrap();
+  assert_eq!(refused.status, Some(status::ServiceUnavailable));
+  assert!(refused.headers.get::<Connection>().is_some());
+}
+
+#[test]
+fn test_drain_gives_up_after_the_timeout() {
+  let in_flight = InFlight::new();
+  let _stuck = in_flight.count();
+  assert!(!in_flight.drain(Duration::from_millis(100)));
+}
@@ -0,0 +1,223 @@
+// Integration tests: requests go through `app()`, the router and the
+// middleware chain, like they would from a browser, but they are built
+// in memory by iron-test so no socket is bound.
+
+use config::Limits;
+use iron::headers::ContentType;
+use iron::prelude::*;
+use iron::{status, Headers};
+use iron::AroundMiddleware;
+use iron_test::{request, response};
+use serde_json;
+use shutdown::InFlight;
+use std::time::Duration;
+
+
+const HOST: &str = "http://localhost:3000";
+
+fn app() -> Chain {
+    super::app(&Limits::default())
+}
+
+// The chain answers unknown routes with an Err, the response is the same
+fn send(result: IronResult<Response>) -> Response {
+    match result {
+        Ok(response) => response,
+        Err(err) => err.response,
+    }
+}
+
+fn get(path: &str) -> Response {
+    send(request::get(&format!("{}{}", HOST, path), Headers::new(), &app()))
+}
+
+fn post(path: &str, body: &str) -> Response {
+    let mut headers = Headers::new();
+    headers.set(ContentType::form_url_encoded());
+    send(request::post(&format!("{}{}", HOST, path), headers, body, &app()))
+}
+
+fn content_type(response: &Response) -> String {
+    match response.headers.get::<ContentType>() {
+        Some(content_type) => content_type.to_string(),
+        None => String::new(),
+    }
+}
+
+
+#[test]
+fn test_get_form() {
+  let response = get("/");
+  assert_eq!(response.status, Some(status::Ok));
+  assert_eq!(content_type(&response), "text/html; charset=utf-8");
+  let body = response::extract_body_to_string(response);
+  assert!(body.contains(r#"<form action="/gcd" method="post">"#));
+  assert_eq!(body.matches(r#"name="n""#).count(), 2);
+}
+
+#[test]
+fn test_every_form_page() {
+  for path in &["/", "/lcm", "/egcd", "/modinv", "/factor", "/batch"] {
+      let response = get(path);
+      assert_eq!(response.status, Some(status::Ok), "GET {}", path);
+      assert_eq!(content_type(&response), "text/html; charset=utf-8", "GET {}", path);
+  }
+}
+
+#[test]
+fn test_post_gcd() {
+  let response = post("/gcd", "n=12&n=18&n=30");
+  assert_eq!(response.status, Some(status::Ok));
+  assert_eq!(content_type(&response), "text/html; charset=utf-8");
+  let body = response::extract_body_to_string(response);
+  assert!(body.contains("The greatest common divisor of the numbers [12, 18, 30] is <b>6</b>"));
+}
+
+#[test]
+fn test_post_gcd_form_errors() {
+  // (body, status, message shown on the page)
+  let cases = [
+      ("", status::BadRequest, "Error parsing form data"),
+      ("m=12", status::BadRequest, "form data has no &#x27;n&#x27; parameter"),
+      ("n=12&m=18", status::Ok, "<b>12</b>"),
+      ("n=", status::BadRequest, "Value for &#x27;n&#x27; is not a number: &quot;&quot;"),
+      ("n=12&n=abc", status::BadRequest, "Value for &#x27;n&#x27; is not a number: &quot;abc&quot;"),
+      ("n=1.5", status::BadRequest, "is not a number"),
+      ("n=-3", status::BadRequest, "is not a number"),
+      ("n=18446744073709551616", status::BadRequest, "is not a number"),
+      ("n=%FF", status::BadRequest, "is not a number"),
+  ];
+  for &(body, expected_status, message) in cases.iter() {
+      let response = post("/gcd", body);
+      assert_eq!(response.status, Some(expected_status), "POST /gcd {:?}", body);
+      assert_eq!(content_type(&response), "text/html; charset=utf-8", "POST /gcd {:?}", body);
+      let page = response::extract_body_to_string(response);
+      assert!(page.contains(message), "POST /gcd {:?} should show {:?}", body, message);
+  }
+}
+
+#[test]
+fn test_unprocessable_values() {
+  let cases = [
+      ("/lcm", "n=18446744073709551615&n=18446744073709551614"),
+      ("/modinv", "a=4&m=8"),
+      ("/factor", "n=0"),
+  ];
+  for &(path, body) in cases.iter() {
+      assert_eq!(post(path, body).status, Some(status::UnprocessableEntity), "POST {} {:?}", path, body);
+  }
+}
+
+#[test]
+fn test_api_content_type_and_errors() {
+  let response = post("/api/gcd", "n=24&n=81");
+  assert_eq!(response.status, Some(status::Ok));
+  assert_eq!(content_type(&response), "application/json");
+
+  for body in &["", "m=1", "n=abc"] {
+      let response = post("/api/gcd", body);
+      assert_eq!(response.status, Some(status::BadRequest), "POST /api/gcd {:?}", body);
+      assert_eq!(content_type(&response), "application/json", "POST /api/gcd {:?}", body);
+      let error: serde_json::Value =
+          serde_json::from_str(&response::extract_body_to_string(response)).unwrap();
+      assert!(error["error"].is_string(), "POST /api/gcd {:?}", body);
+  }
+}
+
+#[test]
+fn test_batch_content_type() {
+  let response = send(request::post(&format!("{}/batch", HOST), Headers::new(), "4,6\n", &app()));
+  assert_eq!(response.status, Some(status::Ok));
+  assert_eq!(content_type(&response), "text/csv; charset=utf-8");
+  assert_eq!(response::extract_body_to_string(response), "4,6,2,12\r\n");
+}
+
+#[test]
+fn test_unknown_routes_and_methods() {
+  assert_eq!(get("/nope").status, Some(status::NotFound));
+  // the forms are only posted, the answers are only shown after a POST
+  assert_eq!(get("/gcd").status, Some(status::NotFound));
+  assert_eq!(get("/api/gcd").status, Some(status::NotFound));
+  assert_eq!(post("/", "n=1").status, Some(status::NotFound));
+}
+
+#[test]
+fn test_metrics_content_type() {
+  let response = get("/metrics");
+  assert_eq!(response.status, Some(status::Ok));
+  assert_eq!(content_type(&response), "text/plain; version=0.0.4; charset=utf-8");
+}
+
+#[test]
+fn test_too_many_values() {
+  let app = super::app(&Limits { max_numbers: 3, ..Limits::default() });
+  let post = |body: &str| send(request::post(&format!("{}/gcd", HOST), Headers::new(), body, &app));
+
+  assert_eq!(post("n=1&n=2&n=3").status, Some(status::Ok));
+  let response = post("n=1&n=2&n=3&n=4");
+  assert_eq!(response.status, Some(status::PayloadTooLarge));
+  let page = response::extract_body_to_string(response);
+  assert!(page.contains("Too many values for &#x27;n&#x27;, at most 3 are accepted"));
+}
+
+#[test]
+fn test_body_too_large() {
+  let app = super::app(&Limits { max_body_bytes: 16, ..Limits::default() });
+  let post = |path: &str, body: &str| send(request::post(&format!("{}{}", HOST, path), Headers::new(), body, &app));
+
+  assert_eq!(post("/gcd", "n=12&n=18").status, Some(status::Ok));
+  for path in &["/gcd", "/api/gcd"] {
+      let response = post(path, "n=123456789&n=123456789");
+      assert_eq!(response.status, Some(status::PayloadTooLarge), "POST {}", path);
+      assert_eq!(response::extract_body_to_string(response), "Request body is larger than 16 bytes\n");
+  }
+  // uploads have their own limit
+  assert_eq!(post("/batch", "123456789,123456789\n").status, Some(status::Ok));
+}
+
+#[test]
+fn test_upload_too_large() {
+  let app = super::app(&Limits { max_upload_bytes: 16, ..Limits::default() });
+  let post = |body: &str| send(request::post(&format!("{}/batch", HOST), Headers::new(), body, &app));
+
+  assert_eq!(post("4,6\n").status, Some(status::Ok));
+  let response = post("123456789,123456789\n");
+  assert_eq!(response.status, Some(status::PayloadTooLarge));
+  assert_eq!(response::extract_body_to_string(response), "Request body is larger than 16 bytes\n");
+}
+
+#[test]
+fn test_rate_limit() {
+  let app = super::app(&Limits { requests_per_second: 0.001, burst: 3, ..Limits::default() });
+
+  for _ in 0..3 {
+      let response = send(request::get(&format!("{}/", HOST), Headers::new(), &app));
+      assert_eq!(response.status, Some(status::Ok));
+  }
+  let response = send(request::get(&format!("{}/", HOST), Headers::new(), &app));
+  assert_eq!(response.status, Some(status::TooManyRequests));
+  assert_eq!(response.headers.get_raw("Retry-After").unwrap()[0], b"1000".to_vec());
+  // still logged and counted by the after middleware
+  assert!(response.headers.get_raw("X-Request-Id").is_some());
+
+  let unlimited = super::app(&Limits { requests_per_second: 0.0, ..Limits::default() });
+  for _ in 0..100 {
+      let response = send(request::get(&format!("{}/", HOST), Headers::new(), &unlimited));
+      assert_eq!(response.status, Some(status::Ok));
+  }
+}
+
+#[test]
+fn test_shutdown_waits_for_the_batch_body() {
+  let in_flight = InFlight::new();
+  let app = in_flight.clone().around(Box::new(app()));
+
+  let response = send(request::post(&format!("{}/batch", HOST), Headers::new(), "4,6\n", &app));
+  assert_eq!(response.status, Some(status::Ok));
+  // the handler returned, the rows are not written yet
+  assert_eq!(in_flight.requests(), 1);
+  assert!(!in_flight.drain(Duration::from_millis(100)));
+
+  assert_eq!(response::extract_body_to_string(response), "4,6,2,12\r\n");
+  assert_eq!(in_flight.requests(), 0);
+}